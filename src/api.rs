@@ -0,0 +1,329 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::time::Duration;
+
+use crate::config::{Backend, Config};
+use crate::{debug, progress};
+
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5);
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(120);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    temperature: f32,
+    max_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize, Debug)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct OllamaRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    options: OllamaOptions,
+}
+
+#[derive(Serialize, Debug)]
+struct OllamaOptions {
+    temperature: f32,
+    num_predict: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaResponse {
+    message: Option<Message>,
+    #[serde(default)]
+    done: bool,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+impl OllamaResponse {
+    fn usage(&self) -> Option<Usage> {
+        self.done.then(|| Usage {
+            prompt_tokens: self.prompt_eval_count.unwrap_or(0),
+            completion_tokens: self.eval_count.unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+impl ChatResponse {
+    fn into_content(self) -> Result<String, Box<dyn std::error::Error>> {
+        match self.choices.into_iter().next() {
+            Some(choice) => Ok(choice.message.content),
+            None => Err("The API returned no choices (the response may have been blocked by a content filter)".into()),
+        }
+    }
+}
+
+impl From<OllamaResponse> for ChatResponse {
+    fn from(response: OllamaResponse) -> ChatResponse {
+        ChatResponse {
+            choices: response.message.map(|message| Choice { message }).into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize, Debug)]
+struct Delta {
+    content: Option<String>,
+}
+
+async fn post_chat(
+    client: &Client,
+    api_key: &str,
+    messages: Vec<Message>,
+    config: &Config,
+    stream: bool,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    let params = config.params_for(&config.model);
+
+    let url = config.chat_url();
+    let body = match config.backend {
+        Backend::NvidiaCompat => serde_json::to_string(&ChatRequest {
+            model: config.model.clone(),
+            messages,
+            stream,
+            temperature: params.temperature,
+            max_tokens: params.max_tokens,
+            stop: params.stop,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+        })?,
+        Backend::Ollama => serde_json::to_string(&OllamaRequest {
+            model: config.model.clone(),
+            messages,
+            stream,
+            options: OllamaOptions {
+                temperature: params.temperature,
+                num_predict: params.max_tokens,
+                stop: params.stop,
+            },
+        })?,
+    };
+
+    let auth = if config.backend == Backend::NvidiaCompat { "Authorization: Bearer ***\n" } else { "" };
+    debug::log(config, &format!("request POST {}", url), &format!("{}{}", auth, body));
+
+    let mut request = client.post(&url).header("Content-Type", "application/json").body(body);
+    if config.backend == Backend::NvidiaCompat {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    let mut attempt = 0;
+    let mut rate_limit_waited = Duration::ZERO;
+    loop {
+        let retry_delay = Duration::from_millis(500 * 2u64.pow(attempt.min(16)));
+        let can_retry = attempt < config.api_retries;
+        attempt += 1;
+
+        let pending = request.try_clone().ok_or("API request body cannot be retried")?;
+        let res = match pending.send().await {
+            Ok(res) => res,
+            Err(e) if can_retry && (e.is_connect() || e.is_timeout() || e.is_request()) => {
+                progress(config, format!("Connection failed ({}), retrying in {:?}...", e, retry_delay));
+                tokio::time::sleep(retry_delay).await;
+                continue;
+            },
+            Err(e) => return Err(read_error(e, config)),
+        };
+
+        let status = res.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let wait = res
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+
+            if rate_limit_waited + wait <= MAX_RATE_LIMIT_WAIT {
+                progress(config, format!("Rate limited, waiting {}s...", wait.as_secs()));
+                tokio::time::sleep(wait).await;
+                rate_limit_waited += wait;
+                attempt -= 1;
+                continue;
+            }
+        }
+
+        if status.is_server_error() && can_retry {
+            progress(config, format!("API returned {}, retrying in {:?}...", status, retry_delay));
+            tokio::time::sleep(retry_delay).await;
+            continue;
+        }
+
+        if !status.is_success() {
+            let error_text = res.text().await?;
+            debug::log(config, &format!("response {}", status), &error_text);
+            return Err(format!("API Error from {} ({}): {}", url, status, error_text).into());
+        }
+
+        return Ok(res);
+    }
+}
+
+fn read_error(error: reqwest::Error, config: &Config) -> Box<dyn std::error::Error> {
+    match config.request_timeout() {
+        Some(timeout) if error.is_timeout() => format!(
+            "The API sent no data for {}s, giving up (raise JADE_REQUEST_TIMEOUT_SECS for slow models)", timeout.as_secs()
+        ).into(),
+        _ => error.into(),
+    }
+}
+
+pub async fn send_chat(
+    client: &Client,
+    api_key: &str,
+    messages: Vec<Message>,
+    config: &Config,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let res = post_chat(client, api_key, messages, config, false).await?;
+
+    let raw_body = res.text().await.map_err(|e| read_error(e, config))?;
+    debug::log(config, "response body", &raw_body);
+    let response_json: ChatResponse = match config.backend {
+        Backend::NvidiaCompat => serde_json::from_str(&raw_body)?,
+        Backend::Ollama => serde_json::from_str::<OllamaResponse>(&raw_body)?.into(),
+    };
+    let content = response_json.into_content()?;
+
+    Ok((content, raw_body))
+}
+
+pub async fn stream_chat(
+    client: &Client,
+    api_key: &str,
+    messages: Vec<Message>,
+    config: &Config,
+    mut on_token: impl FnMut(&str),
+) -> Result<(String, String, Option<Usage>), Box<dyn std::error::Error>> {
+    let mut res = post_chat(client, api_key, messages, config, true).await?;
+
+    let mut content = String::new();
+    let mut usage = None;
+    let mut raw_body = String::new();
+    let mut pending: Vec<u8> = Vec::new();
+
+    let read = async {
+        'read: while let Some(chunk) = res.chunk().await.map_err(|e| read_error(e, config))? {
+            pending.extend_from_slice(&chunk);
+
+            while let Some(newline) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim();
+                raw_body.push_str(line);
+                raw_body.push('\n');
+
+                let token = match config.backend {
+                    Backend::NvidiaCompat => {
+                        let Some(data) = line.strip_prefix("data:").map(str::trim) else { continue };
+                        if data == "[DONE]" {
+                            break 'read;
+                        }
+
+                        let event: StreamChunk = serde_json::from_str(data)?;
+                        usage = event.usage.or(usage);
+                        event.choices.into_iter().next().and_then(|c| c.delta.content)
+                    },
+                    Backend::Ollama => {
+                        if line.is_empty() {
+                            continue;
+                        }
+
+                        let event: OllamaResponse = serde_json::from_str(line)?;
+                        usage = event.usage().or(usage);
+                        if event.done && event.message.is_none() {
+                            break 'read;
+                        }
+                        event.message.map(|m| m.content)
+                    },
+                };
+
+                if let Some(token) = token.filter(|t| !t.is_empty()) {
+                    on_token(&token);
+                    content.push_str(&token);
+                }
+            }
+        }
+        Ok::<(), Box<dyn std::error::Error>>(())
+    }
+    .await;
+    debug::log(config, "response stream", &raw_body);
+    read?;
+
+    if content.is_empty() {
+        return Err("The API stream ended without any content (the response may have been blocked by a content filter)".into());
+    }
+
+    Ok((content, raw_body, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_choices_are_an_error_not_a_panic() {
+        let response: ChatResponse = serde_json::from_str(r#"{"choices":[]}"#).unwrap();
+        let error = response.into_content().unwrap_err();
+        assert!(error.to_string().contains("no choices"));
+    }
+}
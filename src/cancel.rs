@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Notify = Notify::const_new();
+
+// Once installed, Ctrl-C cancels the running turn instead of killing Jade.
+pub fn listen() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            REQUESTED.store(true, Ordering::SeqCst);
+            NOTIFY.notify_waiters();
+        }
+    });
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+pub fn reset() {
+    REQUESTED.store(false, Ordering::SeqCst);
+}
+
+pub async fn cancelled() {
+    loop {
+        let notified = NOTIFY.notified();
+        if requested() {
+            return;
+        }
+        notified.await;
+    }
+}
@@ -0,0 +1,106 @@
+use clap::{Parser, Subcommand};
+use jade::output::OutputFormat;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "jade", version, about = "AI Git Tool")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Run a single request non-interactively and exit (0 if it succeeded, 1 if it aborted or failed)
+    pub prompt: Option<String>,
+
+    /// Work in the repository at PATH instead of the current directory, like `git -C`
+    #[arg(short = 'C', value_name = "PATH")]
+    pub directory: Option<PathBuf>,
+
+    /// Model to use for this run, overriding JADE_MODEL and config.toml
+    #[arg(long, value_name = "NAME")]
+    pub model: Option<String>,
+
+    /// API base URL for this run, overriding JADE_BASE_URL and config.toml
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// Run proposed commands without asking for confirmation (same as JADE_AUTO_CONFIRM=1).
+    /// Force-pushes, hard resets and risky plans still need their typed confirmation.
+    /// Every executed command is logged to ~/.jade/audit.log either way
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Keep going after repeated failed turns instead of pausing to ask what to do
+    #[arg(long)]
+    pub resume_on_error: bool,
+
+    /// Lines of context around each change in the diff sent to the model (git diff -U<N>)
+    #[arg(long, value_name = "N")]
+    pub diff_context_lines: Option<u32>,
+
+    /// Show the model the tail of a long-running command's output every SECS seconds so it can stop it early
+    #[arg(long, value_name = "SECS")]
+    pub stream_progress: Option<u64>,
+
+    /// Write a JSON summary of each turn's outcome (success/aborted/error, commands run, rejections) to PATH
+    #[arg(long, value_name = "PATH")]
+    pub status_file: Option<PathBuf>,
+
+    /// Run commands in an interactive bash login shell (bash -lic) so aliases and shell functions from
+    /// your profile and rc files are available. Slower, since those files are sourced before every
+    /// command, and anything they do runs each time too.
+    #[arg(long)]
+    pub login_shell: bool,
+
+    /// Require the model to give a WHY line for every command, shown before it runs
+    #[arg(long)]
+    pub require_rationale: bool,
+
+    /// Explain each command in plain English before it runs (one extra model request per command)
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Refuse to run anything but git commands (same as JADE_GIT_ONLY=1)
+    #[arg(long)]
+    pub git_only: bool,
+
+    /// Print the commands the model wants to run without executing any of them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Hide progress chatter (thinking, executing, token counts) and show only results, errors and answers
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+
+    /// Output format: human-readable text, or one JSON event per line (prompt, command-executed, command-output, final-answer, error)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
+
+    /// Remove the API key stored in the system keyring and exit
+    #[arg(long)]
+    pub logout: bool,
+
+    /// Log every API request (with the key masked) and raw response to ~/.jade/debug.log (same as JADE_DEBUG=1)
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Start with an empty conversation instead of resuming the saved session
+    #[arg(long)]
+    pub fresh: bool,
+
+    /// Skip the welcome banner at startup (same as JADE_NO_BANNER=1)
+    #[arg(long)]
+    pub no_banner: bool,
+
+    /// Disable colored output (also enabled by setting NO_COLOR)
+    #[arg(long)]
+    pub no_color: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Draft a Markdown pull request description for the current branch (runs no commands)
+    PrDescription {
+        /// Branch to compare against (defaults to the repository's default branch)
+        base: Option<String>,
+    },
+}
@@ -0,0 +1,152 @@
+use console::style;
+use dialoguer::{Editor, Select};
+use reqwest::Client;
+use std::path::Path;
+
+use crate::api::{send_chat, Message};
+use crate::config::Config;
+use crate::output::notice;
+use crate::{git, progress, safety, shell, truncate_diff};
+
+const COMMIT_PROMPT: &str = "You write git commit messages. Given a staged diff, write a Conventional Commits message: \
+    a `type(scope): summary` subject under 72 characters in the imperative mood, then a blank line and a short body \
+    explaining what changed and why when the diff is not self-explanatory. \
+    Reply with the commit message only, without code fences, and do not use the EXECUTE or FINAL protocol.";
+
+const MAX_COMMIT_DIFF_CHARS: usize = 16_000;
+
+pub async fn draft_message(client: &Client, api_key: &str, config: &Config, work_dir: Option<&Path>) -> Result<String, Box<dyn std::error::Error>> {
+    let stat = git::output(work_dir, &["--no-pager", "diff", "--cached", "--stat"])?;
+    if stat.trim().is_empty() {
+        return Err("Nothing is staged. Stage changes with git add first.".into());
+    }
+    let diff = truncate_diff(git::output(work_dir, &["--no-pager", "diff", "--cached"])?, MAX_COMMIT_DIFF_CHARS);
+
+    progress(config, "Drafting a commit message for the staged changes...");
+
+    let messages = vec![
+        Message { role: "system".to_string(), content: COMMIT_PROMPT.to_string() },
+        Message { role: "user".to_string(), content: config.redactor.redact(&format!("DIFF STAT:\n{}\n\nDIFF:\n{}", stat.trim(), diff)) },
+    ];
+
+    let (content, _) = send_chat(client, api_key, messages, config).await?;
+    let message = content.trim().trim_start_matches("```").trim_end_matches("```").trim().to_string();
+    if message.is_empty() {
+        return Err("The model returned an empty commit message".into());
+    }
+    Ok(message)
+}
+
+pub struct CommitCommand {
+    args: Vec<String>,
+    message: String,
+}
+
+pub enum CommitReview {
+    Unchanged,
+    Edited(String),
+    Cancelled,
+}
+
+impl CommitCommand {
+    pub fn parse(command: &str) -> Option<CommitCommand> {
+        let args = shell::split(command)?;
+        if args.first().map(String::as_str) != Some("git") || !args.iter().any(|a| a == "commit") {
+            return None;
+        }
+
+        let mut kept = Vec::new();
+        let mut paragraphs = Vec::new();
+        let mut iter = args.into_iter();
+
+        while let Some(arg) = iter.next() {
+            if arg == "-m" || arg == "--message" {
+                paragraphs.push(iter.next()?);
+            } else if let Some(message) = arg.strip_prefix("--message=") {
+                paragraphs.push(message.to_string());
+            } else if let Some(cluster) = arg.strip_prefix('-').filter(|c| !c.starts_with('-') && c.contains('m')) {
+                let (flags, message) = cluster.split_once('m')?;
+                if !flags.is_empty() {
+                    kept.push(format!("-{}", flags));
+                }
+                if message.is_empty() {
+                    paragraphs.push(iter.next()?);
+                } else {
+                    paragraphs.push(message.to_string());
+                }
+            } else {
+                kept.push(arg);
+            }
+        }
+
+        if paragraphs.is_empty() {
+            return None;
+        }
+
+        Some(CommitCommand { args: kept, message: paragraphs.join("\n\n") })
+    }
+
+    pub fn with_message(&self, message: &str) -> String {
+        let mut args = self.args.clone();
+        args.push("-m".to_string());
+        args.push(message.to_string());
+        shell::join(&args)
+    }
+
+    pub fn review(&self, config: &Config, hooks: &[&str]) -> Result<CommitReview, Box<dyn std::error::Error>> {
+        notice(config, style("Proposed commit message:").bold());
+        for line in self.message.lines() {
+            notice(config, format!("  {}", style(line).cyan()));
+        }
+        safety::print_hooks(config, hooks);
+
+        let choice = Select::new()
+            .with_prompt("Commit with this message?")
+            .items(["Commit", "Edit message", "Cancel"])
+            .default(0)
+            .interact()?;
+
+        match choice {
+            0 => Ok(CommitReview::Unchanged),
+            1 => {
+                let edited = Editor::new().edit(&self.message)?;
+                match edited.map(|m| m.trim().to_string()) {
+                    Some(m) if m.is_empty() => Ok(CommitReview::Cancelled),
+                    Some(m) if m != self.message => Ok(CommitReview::Edited(m)),
+                    _ => Ok(CommitReview::Unchanged),
+                }
+            },
+            _ => Ok(CommitReview::Cancelled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_message_flags() {
+        let commit = CommitCommand::parse(r#"git commit -am "subject" -m 'body with $dollar'"#).unwrap();
+        assert_eq!(commit.message, "subject\n\nbody with $dollar");
+        assert_eq!(commit.args, vec!["git", "commit", "-a"]);
+    }
+
+    #[test]
+    fn ignores_commands_without_a_message() {
+        assert!(CommitCommand::parse("git commit").is_none());
+        assert!(CommitCommand::parse("git status").is_none());
+        assert!(CommitCommand::parse("git commit -m \"x\" && git push").is_none());
+    }
+
+    #[test]
+    fn rebuilt_command_quotes_the_edited_message() {
+        let commit = CommitCommand::parse("git commit --message=old").unwrap();
+        let message = "it's `done`\n\ncosts $5 \"now\"";
+        let rebuilt = commit.with_message(message);
+
+        let reparsed = CommitCommand::parse(&rebuilt).unwrap();
+        assert_eq!(reparsed.message, message);
+        assert_eq!(reparsed.args, vec!["git", "commit"]);
+    }
+}
@@ -0,0 +1,62 @@
+use reqwest::Client;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::api::{send_chat, Message};
+use crate::{git, progress};
+
+const COMPARE_PROMPT: &str = "You explain how two git branches relate to each other. \
+    Using only the commit lists and diff stat provided, describe in plain English what each side has that the other does not, \
+    what areas of the code the divergence touches, and what it would take to bring them together (fast-forward, merge or rebase). \
+    Be concise, do not invent details, and do not use the EXECUTE or FINAL protocol.";
+
+const MAX_COMMITS: usize = 50;
+
+pub async fn explain(
+    client: &Client,
+    api_key: &str,
+    config: &Config,
+    a: &str,
+    b: &str,
+    work_dir: Option<&Path>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    for name in [a, b] {
+        if !git::ref_exists(name, work_dir) {
+            return Err(format!("'{}' is not a branch, tag or commit in this repository", name).into());
+        }
+    }
+
+    let only_in_b = git::output(work_dir, &["log", "--oneline", "--no-decorate", &format!("-n{}", MAX_COMMITS), &format!("{}..{}", a, b)])?;
+    let only_in_a = git::output(work_dir, &["log", "--oneline", "--no-decorate", &format!("-n{}", MAX_COMMITS), &format!("{}..{}", b, a)])?;
+
+    let relationship = match (only_in_a.trim().is_empty(), only_in_b.trim().is_empty()) {
+        (true, true) => return Ok(format!("{} and {} point to the same commit, there is no divergence.", a, b)),
+        (true, false) => format!("{} is an ancestor of {}: {} can be fast-forwarded to {}.", a, b, a, b),
+        (false, true) => format!("{} is an ancestor of {}: {} can be fast-forwarded to {}.", b, a, b, a),
+        (false, false) => "The branches have diverged: each has commits the other lacks.".to_string(),
+    };
+
+    let stat = git::output(work_dir, &["--no-pager", "diff", "--stat", &format!("{}...{}", a, b)])?;
+    let or_none = |log: &str| if log.trim().is_empty() { "(none)".to_string() } else { log.trim().to_string() };
+
+    progress(config, format!("Comparing {} and {}...", a, b));
+
+    let messages = vec![
+        Message { role: "system".to_string(), content: COMPARE_PROMPT.to_string() },
+        Message {
+            role: "user".to_string(),
+            content: config.redactor.redact(&format!(
+                "{}\n\nCommits in {} but not in {} (newest first, at most {}):\n{}\n\n\
+                Commits in {} but not in {} (newest first, at most {}):\n{}\n\n\
+                Changes on {} since it diverged from {} (git diff --stat {}...{}):\n{}",
+                relationship,
+                b, a, MAX_COMMITS, or_none(&only_in_b),
+                a, b, MAX_COMMITS, or_none(&only_in_a),
+                b, a, a, b, or_none(&stat),
+            )),
+        },
+    ];
+
+    let (content, _) = send_chat(client, api_key, messages, config).await?;
+    Ok(content.trim().to_string())
+}
@@ -0,0 +1,78 @@
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
+
+use crate::git;
+
+pub type JadeEditor = Editor<JadeHelper, DefaultHistory>;
+
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "add", "bisect", "blame", "branch", "checkout", "cherry-pick", "clean", "clone", "commit", "diff", "fetch", "grep", "init", "log",
+    "merge", "mv", "pull", "push", "rebase", "reflog", "remote", "reset", "restore", "revert", "rm", "show", "stash", "status",
+    "switch", "tag", "worktree",
+];
+
+// Tab completes git subcommands after `git`, and branch, tag and remote ref names anywhere else.
+#[derive(Default)]
+pub struct JadeHelper {
+    pub work_dir: Option<PathBuf>,
+}
+
+impl Completer for JadeHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(candidates(line, pos, || git::list_refs(self.work_dir.as_deref())))
+    }
+}
+
+impl Hinter for JadeHelper {
+    type Hint = String;
+}
+
+impl Highlighter for JadeHelper {}
+
+impl Validator for JadeHelper {}
+
+impl Helper for JadeHelper {}
+
+fn candidates(line: &str, pos: usize, refs: impl FnOnce() -> Vec<String>) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before.char_indices().rev().find(|(_, c)| c.is_whitespace()).map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &before[start..];
+
+    let options = match before[..start].split_whitespace().next_back() {
+        Some("git") => GIT_SUBCOMMANDS.iter().map(|s| s.to_string()).collect(),
+        Some(_) => refs(),
+        None if word.is_empty() => Vec::new(),
+        None => vec!["git".to_string()],
+    };
+    (start, options.into_iter().filter(|option| option.starts_with(word)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refs() -> Vec<String> {
+        vec!["main".to_string(), "feature/login".to_string(), "origin/main".to_string()]
+    }
+
+    #[test]
+    fn completes_subcommands_after_git_and_refs_elsewhere() {
+        assert_eq!(candidates("git ch", 6, refs), (4, vec!["checkout".to_string(), "cherry-pick".to_string()]));
+        assert_eq!(candidates("git rebase ma", 13, refs), (11, vec!["main".to_string()]));
+        assert_eq!(candidates("merge fe into main", 8, refs), (6, vec!["feature/login".to_string()]));
+        assert_eq!(candidates("gi", 2, refs), (0, vec!["git".to_string()]));
+        assert_eq!(candidates("/mod", 4, refs), (0, Vec::new()));
+    }
+
+    #[test]
+    fn handles_multibyte_whitespace() {
+        assert_eq!(candidates("git\u{a0}ch", 7, refs), (5, vec!["checkout".to_string(), "cherry-pick".to_string()]));
+    }
+}
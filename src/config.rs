@@ -0,0 +1,378 @@
+use reqwest::{Certificate, Client, Proxy};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs};
+use toml_edit::DocumentMut;
+
+use crate::exec::{ExecLimits, ShellExecutor};
+use crate::output::OutputFormat;
+use crate::redact::Redactor;
+use crate::safety::CommandPolicy;
+
+const DEFAULT_BASE_URL: &str = "https://integrate.api.nvidia.com/v1";
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+const DEFAULT_MODEL: &str = "moonshotai/kimi-k2.5";
+const DEFAULT_TEMPERATURE: f32 = 0.3;
+const DEFAULT_MAX_TOKENS: usize = 4096;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const DEFAULT_SUGGESTION: &str = "Try: 'summarize my uncommitted changes'";
+
+#[derive(Deserialize, Default, Clone)]
+pub struct ModelParams {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<usize>,
+    pub stop: Option<Vec<String>>,
+}
+
+impl ModelParams {
+    fn overridden_by(&self, other: &ModelParams) -> ModelParams {
+        ModelParams {
+            temperature: other.temperature.or(self.temperature),
+            max_tokens: other.max_tokens.or(self.max_tokens),
+            stop: other.stop.clone().or_else(|| self.stop.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Backend {
+    NvidiaCompat,
+    Ollama,
+}
+
+impl Backend {
+    pub fn from_env() -> Result<Backend, String> {
+        match env::var("JADE_BACKEND").unwrap_or_default().trim().to_lowercase().as_str() {
+            "" | "nvidia" | "openai" => Ok(Backend::NvidiaCompat),
+            "ollama" => Ok(Backend::Ollama),
+            other => Err(format!("Unknown JADE_BACKEND '{}', expected 'nvidia' or 'ollama'", other)),
+        }
+    }
+
+    pub fn needs_api_key(&self) -> bool {
+        *self == Backend::NvidiaCompat
+    }
+}
+
+pub struct ResolvedParams {
+    pub temperature: f32,
+    pub max_tokens: usize,
+    pub stop: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    api_key: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+    #[serde(flatten)]
+    defaults: ModelParams,
+    #[serde(default)]
+    models: HashMap<String, ModelParams>,
+    #[serde(default)]
+    retry_limits: HashMap<String, u32>,
+    suggestion: Option<String>,
+    default_branch: Option<String>,
+    proxy: Option<String>,
+    ca_cert: Option<PathBuf>,
+    #[serde(default)]
+    redact_patterns: Vec<String>,
+    max_attempts: Option<u32>,
+    #[serde(default)]
+    allowlist: Vec<String>,
+    #[serde(default)]
+    blocklist: Vec<String>,
+    allow_sudo: Option<bool>,
+    allow_hard_reset: Option<bool>,
+    block_force_push: Option<bool>,
+}
+
+pub struct Config {
+    pub api_key: Option<String>,
+    pub backend: Backend,
+    pub base_url: String,
+    pub model: String,
+    pub autosave_secs: u64,
+    pub history_size: usize,
+    pub session_max_bytes: usize,
+    pub diff_exclude: Vec<String>,
+    pub diff_context_lines: Option<u32>,
+    pub max_consecutive_errors: u32,
+    pub api_retries: u32,
+    pub response_filter: Option<String>,
+    pub max_output_bytes: usize,
+    pub max_output_lines: usize,
+    pub feedback_max_chars: usize,
+    pub feedback_max_lines: usize,
+    pub context_budget_tokens: usize,
+    pub summarize_after: usize,
+    pub price_per_1k_tokens: Option<f64>,
+    pub command_timeout_secs: u64,
+    pub stream_progress_secs: Option<u64>,
+    pub max_tracked_files: usize,
+    pub login_shell: bool,
+    pub auto_confirm: bool,
+    pub dry_run: bool,
+    pub quiet: bool,
+    pub prompt: Option<String>,
+    pub no_banner: bool,
+    pub explain: bool,
+    pub git_only: bool,
+    pub allow_sudo: bool,
+    pub allow_hard_reset: bool,
+    // Force-pushes normally run after the branch name is typed; teams that never want them can refuse them outright.
+    pub block_force_push: bool,
+    pub max_attempts: u32,
+    pub output_format: OutputFormat,
+    pub audit_log: Option<PathBuf>,
+    pub debug_log: Option<PathBuf>,
+    pub require_rationale: bool,
+    pub retry_limits: HashMap<String, u32>,
+    pub suggestion: Option<String>,
+    pub default_branch: Option<String>,
+    pub redactor: Redactor,
+    pub policy: CommandPolicy,
+    pub config_file: PathBuf,
+    pub proxy: Option<String>,
+    pub request_timeout_secs: u64,
+    pub ca_cert: Option<PathBuf>,
+    model_defaults: ModelParams,
+    models: HashMap<String, ModelParams>,
+    pub param_overrides: ModelParams,
+}
+
+impl Config {
+    pub fn load(jade_dir: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let path = config_path(jade_dir);
+        let file: ConfigFile = match fs::read_to_string(&path) {
+            Ok(raw) => toml::from_str(&raw).map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+            Err(_) => ConfigFile::default(),
+        };
+
+        let backend = Backend::from_env()?;
+        let model = match env::var("JADE_MODEL").ok().or(file.model) {
+            Some(model) if model.trim().is_empty() => return Err("The configured model name is empty".into()),
+            Some(model) => model.trim().to_string(),
+            None if backend == Backend::Ollama => {
+                return Err("Set JADE_MODEL or `model` in config.toml to a local model (e.g. llama3.1) when JADE_BACKEND=ollama".into());
+            },
+            None => DEFAULT_MODEL.to_string(),
+        };
+
+        let default_base_url = match backend {
+            Backend::NvidiaCompat => DEFAULT_BASE_URL,
+            Backend::Ollama => DEFAULT_OLLAMA_URL,
+        };
+
+        let base_url = env::var("JADE_BASE_URL")
+            .ok()
+            .or(file.base_url)
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| default_base_url.to_string())
+            .trim()
+            .trim_end_matches('/')
+            .to_string();
+
+        let redactor = Redactor::new(&file.redact_patterns)
+            .map_err(|e| format!("Invalid redact_patterns in {}: {}", path.display(), e))?;
+
+        Ok(Config {
+            api_key: file.api_key.filter(|key| !key.trim().is_empty()),
+            backend,
+            base_url,
+            model,
+            autosave_secs: env_parse("JADE_AUTOSAVE_SECS").or_else(|| env_parse("JADE_AUTOSAVE_IDLE_SECS")).unwrap_or(60),
+            history_size: env_parse("JADE_HISTORY_SIZE").unwrap_or(1000),
+            session_max_bytes: env_parse("JADE_SESSION_MAX_BYTES").unwrap_or(1024 * 1024),
+            diff_exclude: env_list("JADE_DIFF_EXCLUDE"),
+            diff_context_lines: env_parse("JADE_DIFF_CONTEXT_LINES"),
+            max_consecutive_errors: env_parse("JADE_MAX_CONSECUTIVE_ERRORS").unwrap_or(3),
+            api_retries: env_parse("JADE_API_RETRIES").unwrap_or(3),
+            response_filter: env::var("JADE_RESPONSE_FILTER").ok().filter(|v| !v.trim().is_empty()),
+            max_output_bytes: env_parse("JADE_MAX_OUTPUT_BYTES").unwrap_or(1024 * 1024),
+            max_output_lines: env_parse("JADE_MAX_OUTPUT_LINES").unwrap_or(20_000),
+            feedback_max_chars: env_parse("JADE_FEEDBACK_MAX_CHARS").unwrap_or(16_000),
+            feedback_max_lines: env_parse("JADE_FEEDBACK_MAX_LINES").unwrap_or(400),
+            context_budget_tokens: env_parse("JADE_CONTEXT_BUDGET_TOKENS").unwrap_or(64_000),
+            summarize_after: if env_flag("JADE_SUMMARIZE_HISTORY") {
+                env_parse("JADE_SUMMARIZE_AFTER").unwrap_or(40)
+            } else {
+                0
+            },
+            price_per_1k_tokens: env_parse("JADE_PRICE_PER_1K_TOKENS"),
+            command_timeout_secs: env_parse("JADE_COMMAND_TIMEOUT_SECS").unwrap_or(30),
+            stream_progress_secs: env_parse("JADE_STREAM_PROGRESS_SECS").filter(|secs| *secs > 0),
+            login_shell: env_flag("JADE_LOGIN_SHELL"),
+            auto_confirm: env_flag("JADE_AUTO_CONFIRM"),
+            dry_run: env_flag("JADE_DRY_RUN"),
+            quiet: env_flag("JADE_QUIET"),
+            prompt: env::var("JADE_PROMPT").ok().filter(|p| !p.trim().is_empty()),
+            no_banner: env_flag("JADE_NO_BANNER"),
+            explain: env_flag("JADE_EXPLAIN"),
+            git_only: env_flag("JADE_GIT_ONLY"),
+            allow_sudo: env_bool("JADE_ALLOW_SUDO").or(file.allow_sudo).unwrap_or(false),
+            allow_hard_reset: env_bool("JADE_ALLOW_HARD_RESET").or(file.allow_hard_reset).unwrap_or(false),
+            block_force_push: env_bool("JADE_BLOCK_FORCE_PUSH").or(file.block_force_push).unwrap_or(false),
+            max_attempts: env_parse("JADE_MAX_ATTEMPTS").or(file.max_attempts).unwrap_or(10),
+            output_format: OutputFormat::default(),
+            audit_log: Some(jade_dir.join("audit.log")),
+            debug_log: env_flag("JADE_DEBUG").then(|| jade_dir.join("debug.log")),
+            require_rationale: env_flag("JADE_REQUIRE_RATIONALE"),
+            max_tracked_files: env_parse("JADE_MAX_TRACKED_FILES").unwrap_or(50_000),
+            retry_limits: file.retry_limits,
+            suggestion: env::var("JADE_SUGGESTION")
+                .ok()
+                .or(file.suggestion)
+                .or_else(|| Some(DEFAULT_SUGGESTION.to_string()))
+                .filter(|s| !s.trim().is_empty()),
+            default_branch: env::var("JADE_DEFAULT_BRANCH").ok().or(file.default_branch).filter(|b| !b.trim().is_empty()),
+            redactor,
+            policy: CommandPolicy { allowlist: file.allowlist, blocklist: file.blocklist },
+            config_file: path,
+            request_timeout_secs: env_parse("JADE_REQUEST_TIMEOUT_SECS").unwrap_or(120),
+            proxy: env::var("JADE_PROXY").ok().filter(|v| !v.trim().is_empty()).or(file.proxy),
+            ca_cert: env::var_os("JADE_CA_CERT").map(PathBuf::from).or(file.ca_cert),
+            model_defaults: file.defaults,
+            models: file.models,
+            param_overrides: ModelParams {
+                temperature: env_parse("JADE_TEMPERATURE"),
+                max_tokens: env_parse("JADE_MAX_TOKENS"),
+                stop: None,
+            },
+        })
+    }
+
+    pub fn chat_url(&self) -> String {
+        match self.backend {
+            Backend::NvidiaCompat => format!("{}/chat/completions", self.base_url),
+            Backend::Ollama => format!("{}/api/chat", self.base_url),
+        }
+    }
+
+    pub fn exec_limits(&self) -> ExecLimits {
+        ExecLimits {
+            max_bytes: self.max_output_bytes,
+            max_lines: self.max_output_lines,
+            timeout: (self.command_timeout_secs > 0).then(|| Duration::from_secs(self.command_timeout_secs)),
+        }
+    }
+
+    pub fn executor(&self) -> ShellExecutor {
+        ShellExecutor { limits: self.exec_limits(), login_shell: self.login_shell, echo: !self.quiet }
+    }
+
+    pub fn params_for(&self, model: &str) -> ResolvedParams {
+        let short_name = model.rsplit('/').next().unwrap_or(model);
+        let overrides = self.models.get(model).or_else(|| self.models.get(short_name));
+
+        let params = match overrides {
+            Some(overrides) => self.model_defaults.overridden_by(overrides),
+            None => self.model_defaults.clone(),
+        }
+        .overridden_by(&self.param_overrides);
+
+        ResolvedParams {
+            temperature: params.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            max_tokens: params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            stop: params.stop,
+        }
+    }
+
+    // HTTPS_PROXY and friends are honored by default; `proxy` overrides them and `ca_cert` adds roots for TLS interception.
+    pub fn http_client(&self) -> Result<Client, Box<dyn std::error::Error>> {
+        // An idle timeout rather than a total one, so long streamed answers are fine as long as data keeps arriving.
+        let mut builder = Client::builder().connect_timeout(CONNECT_TIMEOUT);
+        if let Some(timeout) = self.request_timeout() {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.trim()).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?);
+        }
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path).map_err(|e| format!("Could not read CA certificate {}: {}", path.display(), e))?;
+            let certs = Certificate::from_pem_bundle(&pem).map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+            builder = builder.tls_certs_merge(certs);
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        (self.request_timeout_secs > 0).then(|| Duration::from_secs(self.request_timeout_secs))
+    }
+
+    pub fn autosave_interval(&self) -> Option<Duration> {
+        (self.autosave_secs > 0).then(|| Duration::from_secs(self.autosave_secs))
+    }
+
+    pub fn stream_progress(&self) -> Option<Duration> {
+        self.stream_progress_secs.map(Duration::from_secs)
+    }
+}
+
+pub fn config_path(jade_dir: &Path) -> PathBuf {
+    jade_dir.join("config.toml")
+}
+
+pub fn save_api_key(jade_dir: &Path, api_key: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = config_path(jade_dir);
+    save_setting(&path, "api_key", api_key)?;
+    Ok(path)
+}
+
+pub fn save_model(config_file: &Path, model: &str) -> Result<(), Box<dyn std::error::Error>> {
+    save_setting(config_file, "model", model)
+}
+
+// Edits just this key, keeping the user's comments, formatting and key order.
+fn save_setting(path: &Path, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut document: DocumentMut = match fs::read_to_string(path) {
+        Ok(raw) => raw.parse().map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+        Err(_) => DocumentMut::new(),
+    };
+
+    document[key] = toml_edit::value(value);
+    fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|v| v.trim().parse::<T>().ok())
+}
+
+fn env_flag(name: &str) -> bool {
+    env_bool(name).unwrap_or(false)
+}
+
+// Some when the variable is set, so it can override the matching config.toml key either way.
+fn env_bool(name: &str) -> Option<bool> {
+    env::var(name).ok().map(|v| v.trim() == "1" || v.trim().eq_ignore_ascii_case("true"))
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|v| {
+            v.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_a_setting_keeps_the_rest_of_the_file() {
+        let path = env::temp_dir().join(format!("jade-save-setting-{}.toml", std::process::id()));
+        fs::write(&path, "# my settings\nmax_attempts = 5 # tuned\nmodel = \"old\"\n").unwrap();
+
+        save_model(&path, "new").unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved, "# my settings\nmax_attempts = 5 # tuned\nmodel = \"new\"\n");
+    }
+}
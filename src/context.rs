@@ -0,0 +1,96 @@
+use reqwest::Client;
+
+use crate::config::Config;
+use crate::api::{send_chat, Message};
+
+const CHARS_PER_TOKEN: usize = 4;
+const SUMMARY_PROMPT: &str = "Summarize the following git session so far for the assistant that will continue it. \
+    Keep the user's goals, the commands that were run and what they showed, decisions made, and anything still unresolved. \
+    Be concise and reply with the summary only.";
+
+pub fn estimate_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(|m| m.content.len().div_ceil(CHARS_PER_TOKEN) + 4).sum()
+}
+
+/// Drops the oldest history until it fits in `budget` tokens alongside the system message. The
+/// latest message is always kept, and the history never starts with an assistant reply.
+pub fn trim_to_budget(history: &mut Vec<Message>, system: &Message, budget: usize) -> usize {
+    let system_tokens = estimate_tokens(std::slice::from_ref(system));
+    let mut dropped = 0;
+
+    while history.len() > 1 && system_tokens + estimate_tokens(history) > budget {
+        history.remove(0);
+        dropped += 1;
+        while history.len() > 1 && history[0].role != "user" {
+            history.remove(0);
+            dropped += 1;
+        }
+    }
+
+    dropped
+}
+
+/// Replaces the oldest half of the history with a single model-written summary. Returns the number
+/// of messages that were folded in, or 0 if there was nothing worth summarizing.
+pub async fn summarize_oldest(
+    client: &Client,
+    api_key: &str,
+    config: &Config,
+    history: &mut Vec<Message>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let Some(split) = (history.len() / 2..history.len() - 1).find(|&i| i > 0 && history[i].role == "user") else {
+        return Ok(0);
+    };
+
+    let transcript = history[..split]
+        .iter()
+        .map(|m| format!("{}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let messages = vec![
+        Message { role: "system".to_string(), content: SUMMARY_PROMPT.to_string() },
+        Message { role: "user".to_string(), content: transcript },
+    ];
+
+    let (summary, _) = send_chat(client, api_key, messages, config).await?;
+    history.splice(..split, [Message {
+        role: "system".to_string(),
+        content: format!("Summary of the earlier part of this session:\n{}", summary.trim()),
+    }]);
+    Ok(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message { role: role.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn drops_oldest_exchanges_first() {
+        let system = message("system", "");
+        let mut history = vec![
+            message("user", &"a".repeat(400)),
+            message("assistant", &"b".repeat(400)),
+            message("user", &"c".repeat(400)),
+            message("assistant", &"d".repeat(400)),
+            message("user", "latest"),
+        ];
+
+        assert_eq!(trim_to_budget(&mut history, &system, 250), 2);
+        assert_eq!(history.len(), 3);
+        assert!(history[0].content.starts_with('c'));
+    }
+
+    #[test]
+    fn keeps_the_latest_message_even_over_budget() {
+        let system = message("system", "");
+        let mut history = vec![message("user", "old"), message("assistant", "reply"), message("user", &"x".repeat(4000))];
+
+        trim_to_budget(&mut history, &system, 10);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].content.len(), 4000);
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Violation {
+    MissingPrefix,
+    MixedFinalExecute,
+    NoProtocol,
+    InlineExecute,
+    Destructive,
+    UserRejected,
+    BadVariable,
+    Privileged,
+    Interactive,
+    EmptyExecute,
+    MissingRationale,
+    NotGit,
+    Policy,
+}
+
+impl Violation {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Violation::MissingPrefix => "missing_prefix",
+            Violation::MixedFinalExecute => "mixed_final_execute",
+            Violation::NoProtocol => "no_protocol",
+            Violation::InlineExecute => "inline_execute",
+            Violation::Destructive => "destructive",
+            Violation::UserRejected => "user_rejected",
+            Violation::BadVariable => "bad_variable",
+            Violation::Privileged => "privileged",
+            Violation::Interactive => "interactive",
+            Violation::EmptyExecute => "empty_execute",
+            Violation::MissingRationale => "missing_rationale",
+            Violation::NotGit => "not_git",
+            Violation::Policy => "policy",
+        }
+    }
+
+    fn default_limit(&self) -> u32 {
+        match self {
+            Violation::MissingPrefix | Violation::NoProtocol | Violation::MissingRationale => 3,
+            Violation::MixedFinalExecute | Violation::InlineExecute | Violation::BadVariable | Violation::EmptyExecute => 2,
+            Violation::UserRejected | Violation::NotGit | Violation::Policy | Violation::Interactive => 2,
+            Violation::Destructive | Violation::Privileged => 1,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Violation::MissingPrefix => "kept sending lines without an EXECUTE prefix",
+            Violation::MixedFinalExecute => "kept mixing FINAL and EXECUTE",
+            Violation::NoProtocol => "kept replying without FINAL or EXECUTE",
+            Violation::InlineExecute => "kept putting several commands on one EXECUTE line",
+            Violation::Destructive => "kept proposing blocked destructive commands",
+            Violation::UserRejected => "kept proposing commands you rejected",
+            Violation::BadVariable => "kept misusing session variables",
+            Violation::Privileged => "kept proposing commands that need sudo",
+            Violation::Interactive => "kept proposing commands that wait for an editor or prompt",
+            Violation::EmptyExecute => "kept sending EXECUTE lines without a command",
+            Violation::MissingRationale => "kept sending EXECUTE lines without a WHY line",
+            Violation::NotGit => "kept proposing commands other than git in git-only mode",
+            Violation::Policy => "kept proposing commands your command policy does not allow",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Corrections {
+    counts: HashMap<Violation, u32>,
+}
+
+impl Corrections {
+    pub fn record(&mut self, violation: Violation) {
+        *self.counts.entry(violation).or_insert(0) += 1;
+    }
+
+    pub fn exhausted(&self, limits: &HashMap<String, u32>) -> Option<Violation> {
+        self.counts.iter().find_map(|(violation, count)| {
+            let limit = limits.get(violation.key()).copied().unwrap_or_else(|| violation.default_limit());
+            (*count > limit).then_some(*violation)
+        })
+    }
+}
@@ -0,0 +1,21 @@
+use keyring::Entry;
+
+const SERVICE: &str = "jade";
+const USER: &str = "api_key";
+
+pub fn load_api_key() -> Option<String> {
+    Entry::new(SERVICE, USER).ok()?.get_password().ok().filter(|key| !key.trim().is_empty())
+}
+
+pub fn store_api_key(api_key: &str) -> keyring::Result<()> {
+    Entry::new(SERVICE, USER)?.set_password(api_key)
+}
+
+// Returns false when there was nothing stored to delete.
+pub fn delete_api_key() -> keyring::Result<bool> {
+    match Entry::new(SERVICE, USER)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
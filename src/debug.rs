@@ -0,0 +1,17 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::SystemTime;
+
+use crate::audit::iso_timestamp;
+use crate::config::Config;
+
+pub fn log(config: &Config, label: &str, body: &str) {
+    let Some(path) = &config.debug_log else { return };
+
+    let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| {
+        writeln!(file, "===== {} {}\n{}\n", iso_timestamp(SystemTime::now()), label, body.trim_end())
+    });
+    if let Err(e) = written {
+        eprintln!("Failed to write debug log {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,242 @@
+const MAX_ITEMS: usize = 20;
+const MAX_REFS: usize = 50;
+
+const REF_ERRORS: &[&str] = &[
+    "ambiguous argument",
+    "unknown revision",
+    "not a valid object name",
+    "invalid reference",
+    "bad revision",
+    "couldn't find remote ref",
+    "did not match any file(s) known to git",
+];
+
+struct TestFailure {
+    name: String,
+    location: Option<String>,
+    message: Option<String>,
+}
+
+// Only failed commands are summarized; a successful one's output (say, a file that contains `error:` lines) is passed through as is.
+pub fn summarize_failure(command: &str, stdout: &str, stderr: &str, exit_code: Option<i32>) -> Option<String> {
+    if exit_code == Some(0) {
+        return None;
+    }
+
+    let command = command.trim();
+    let first_word = command.split_whitespace().next().unwrap_or("");
+
+    let combined = format!("{}\n{}", stdout, stderr);
+
+    let sections = match first_word {
+        "cargo" | "rustc" => summarize_cargo(&combined),
+        "git" => summarize_git(&combined),
+        _ => Vec::new(),
+    };
+
+    if sections.is_empty() {
+        return None;
+    }
+
+    let mut summary = format!("Structured summary of `{}` output:\n", command);
+    for section in sections {
+        summary.push_str(&section);
+    }
+    Some(summary)
+}
+
+fn summarize_cargo(output: &str) -> Vec<String> {
+    let mut sections = Vec::new();
+
+    let failures = parse_test_failures(output);
+    if !failures.is_empty() {
+        let mut section = format!("Failing tests ({}):\n", failures.len());
+        for failure in failures.iter().take(MAX_ITEMS) {
+            section.push_str(&format!("- {}", failure.name));
+            if let Some(location) = &failure.location {
+                section.push_str(&format!(" at {}", location));
+            }
+            if let Some(message) = &failure.message {
+                section.push_str(&format!(": {}", message));
+            }
+            section.push('\n');
+        }
+        push_overflow(&mut section, failures.len());
+        sections.push(section);
+    }
+
+    let errors = parse_compiler_errors(output);
+    if !errors.is_empty() {
+        let mut section = format!("Compiler errors ({}):\n", errors.len());
+        for error in errors.iter().take(MAX_ITEMS) {
+            section.push_str(&format!("- {}\n", error));
+        }
+        push_overflow(&mut section, errors.len());
+        sections.push(section);
+    }
+
+    sections
+}
+
+fn parse_test_failures(output: &str) -> Vec<TestFailure> {
+    let mut failures: Vec<TestFailure> = output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("test ")?;
+            let name = rest.strip_suffix(" ... FAILED")?;
+            Some(TestFailure { name: name.to_string(), location: None, message: None })
+        })
+        .collect();
+
+    let lines: Vec<&str> = output.lines().collect();
+    let mut current: Option<usize> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+
+        if let Some(name) = trimmed.strip_prefix("---- ").and_then(|rest| rest.strip_suffix(" stdout ----")) {
+            current = failures.iter().position(|f| f.name == name);
+            continue;
+        }
+
+        let Some(index) = current else { continue };
+        let Some((_, panic)) = trimmed.split_once("panicked at ") else { continue };
+
+        let failure = &mut failures[index];
+        if let Some(old_style) = panic.strip_prefix('\'') {
+            if let Some((message, location)) = old_style.rsplit_once("', ") {
+                failure.message = Some(message.to_string());
+                failure.location = Some(location.trim_end_matches(':').to_string());
+            }
+        } else {
+            failure.location = Some(panic.trim_end_matches(':').to_string());
+            failure.message = lines[i + 1..]
+                .iter()
+                .map(|l| l.trim())
+                .find(|l| !l.is_empty())
+                .filter(|l| !l.starts_with("note:"))
+                .map(|l| l.to_string());
+        }
+        current = None;
+    }
+
+    failures
+}
+
+fn parse_compiler_errors(output: &str) -> Vec<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut errors = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if !(line.starts_with("error[") || line.starts_with("error:")) {
+            continue;
+        }
+
+        if line.starts_with("error: could not compile")
+            || line.starts_with("error: aborting")
+            || line.starts_with("error: test failed")
+            || line.starts_with("error: process didn't exit successfully")
+        {
+            continue;
+        }
+
+        let location = lines[i + 1..]
+            .iter()
+            .take(3)
+            .find_map(|l| l.trim().strip_prefix("--> "));
+
+        match location {
+            Some(location) => errors.push(format!("{} at {}", line, location)),
+            None => errors.push(line.to_string()),
+        }
+    }
+
+    errors
+}
+
+fn summarize_git(output: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut in_error_block = false;
+
+    for line in output.lines() {
+        if line.starts_with("fatal: ") || line.starts_with("error: ") || line.starts_with("CONFLICT ") {
+            problems.push(line.to_string());
+            in_error_block = true;
+        } else if in_error_block && line.starts_with('\t') {
+            problems.push(format!("  {}", line.trim()));
+        } else {
+            in_error_block = false;
+        }
+    }
+
+    if problems.is_empty() {
+        return Vec::new();
+    }
+
+    let mut section = "Git errors:\n".to_string();
+    for problem in problems.iter().take(MAX_ITEMS) {
+        section.push_str(&format!("- {}\n", problem));
+    }
+    push_overflow(&mut section, problems.len());
+    vec![section]
+}
+
+pub fn is_ref_error(output: &str) -> bool {
+    REF_ERRORS.iter().any(|pattern| output.contains(pattern))
+}
+
+pub fn describe_refs(refs: &[String]) -> String {
+    if refs.is_empty() {
+        return "The command referred to a ref that does not exist, and this repository has no branches or tags yet.\n".to_string();
+    }
+
+    let mut section = format!(
+        "The command referred to a ref that does not exist. Pick one of the existing refs ({}) instead of guessing:\n",
+        refs.len()
+    );
+    for name in refs.iter().take(MAX_REFS) {
+        section.push_str(&format!("- {}\n", name));
+    }
+    if refs.len() > MAX_REFS {
+        section.push_str(&format!("... and {} more\n", refs.len() - MAX_REFS));
+    }
+    section
+}
+
+fn push_overflow(section: &mut String, total: usize) {
+    if total > MAX_ITEMS {
+        section.push_str(&format!("... and {} more\n", total - MAX_ITEMS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_failed_commands_are_summarized() {
+        let output = "error: this line is part of the file\n";
+        assert_eq!(summarize_failure("git show HEAD:notes.txt", output, "", Some(0)), None);
+
+        let summary = summarize_failure("git merge topic", "CONFLICT (content): Merge conflict in a.txt\n", "", Some(1)).unwrap();
+        assert!(summary.contains("- CONFLICT (content): Merge conflict in a.txt"));
+    }
+
+    #[test]
+    fn failing_tests_are_parsed_from_cargo_test_output() {
+        let failures = parse_test_failures(include_str!("../tests/fixtures/cargo_test_failures.txt"));
+        let failures: Vec<_> = failures.iter().map(|f| (f.name.as_str(), f.location.as_deref(), f.message.as_deref())).collect();
+        assert_eq!(failures, vec![
+            ("tests::adds", Some("src/lib.rs:7:17"), Some("assertion `left == right` failed")),
+            ("tests::explodes", Some("src/lib.rs:11:21"), Some("boom")),
+        ]);
+    }
+
+    #[test]
+    fn compiler_errors_are_parsed_from_rustc_output() {
+        assert_eq!(parse_compiler_errors(include_str!("../tests/fixtures/rustc_errors.txt")), vec![
+            "error[E0425]: cannot find value `missing` in this scope at src/main.rs:3:20".to_string(),
+            "error[E0308]: mismatched types at src/main.rs:2:22".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{cancel, shell};
+
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+pub struct ExecLimits {
+    pub max_bytes: usize,
+    pub max_lines: usize,
+    pub timeout: Option<Duration>,
+}
+
+pub trait CommandExecutor {
+    fn run(&self, command: &str, work_dir: Option<&Path>, progress: Option<Progress>) -> io::Result<CapturedOutput>;
+}
+
+pub struct ShellExecutor {
+    pub limits: ExecLimits,
+    pub login_shell: bool,
+    pub echo: bool,
+}
+
+impl CommandExecutor for ShellExecutor {
+    fn run(&self, command: &str, work_dir: Option<&Path>, progress: Option<Progress>) -> io::Result<CapturedOutput> {
+        let mut cmd = shell::command_in(command, self.login_shell);
+        if let Some(dir) = work_dir {
+            cmd.current_dir(dir);
+        }
+        run_capped(cmd, &self.limits, self.echo, progress)
+    }
+}
+
+/// Replays canned output instead of running anything, and remembers which commands were asked for.
+/// Commands without a canned reply succeed with no output.
+#[derive(Default)]
+pub struct MockExecutor {
+    replies: HashMap<String, (String, String, i32)>,
+    ran: RefCell<Vec<String>>,
+}
+
+impl MockExecutor {
+    pub fn reply(mut self, command: &str, stdout: &str, stderr: &str, exit_code: i32) -> MockExecutor {
+        self.replies.insert(command.to_string(), (stdout.to_string(), stderr.to_string(), exit_code));
+        self
+    }
+
+    pub fn ran(&self) -> Vec<String> {
+        self.ran.borrow().clone()
+    }
+}
+
+impl CommandExecutor for MockExecutor {
+    fn run(&self, command: &str, _work_dir: Option<&Path>, _progress: Option<Progress>) -> io::Result<CapturedOutput> {
+        self.ran.borrow_mut().push(command.to_string());
+        let (stdout, stderr, exit_code) = self.replies.get(command).cloned().unwrap_or_default();
+
+        Ok(CapturedOutput {
+            stdout: stdout.into_bytes(),
+            stderr: stderr.into_bytes(),
+            exit_code: Some(exit_code),
+            runaway: false,
+            aborted_early: false,
+            timed_out: false,
+            interrupted: false,
+        })
+    }
+}
+
+pub struct Progress<'a> {
+    pub interval: Duration,
+    pub should_abort: &'a mut dyn FnMut(&CapturedOutput, Duration) -> bool,
+}
+
+pub struct CapturedOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: Option<i32>,
+    pub runaway: bool,
+    pub aborted_early: bool,
+    pub timed_out: bool,
+    pub interrupted: bool,
+}
+
+impl CapturedOutput {
+    pub fn success(&self) -> bool {
+        !self.runaway && !self.aborted_early && !self.timed_out && !self.interrupted && self.exit_code == Some(0)
+    }
+
+    pub fn tail(&self, max_lines: usize) -> String {
+        let combined = format!("{}{}", String::from_utf8_lossy(&self.stdout), String::from_utf8_lossy(&self.stderr));
+        let lines: Vec<&str> = combined.lines().collect();
+        lines[lines.len().saturating_sub(max_lines)..].join("\n")
+    }
+}
+
+// Output cut off mid-character (by the runaway limit) is still text; anything else that isn't UTF-8 is binary.
+pub fn decode_output(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(e) if e.error_len().is_none() => String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+        Err(_) => format!("[binary output, {} bytes omitted]", bytes.len()),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// With `echo`, output is also copied to the terminal as it arrives, so slow commands like `git clone` show their progress.
+pub fn run_capped(mut cmd: Command, limits: &ExecLimits, echo: bool, mut progress: Option<Progress>) -> io::Result<CapturedOutput> {
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    // A new session puts the command in its own process group (so the whole tree can be killed) and
+    // detaches it from the terminal, so nothing it spawns can stop on a tty read or grab job control.
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+        cmd.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    let mut child = cmd.spawn()?;
+    let (tx, rx) = mpsc::channel::<(Stream, Vec<u8>)>();
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, Stream::Stdout, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, Stream::Stderr, tx);
+    }
+
+    let mut captured = CapturedOutput {
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        exit_code: None,
+        runaway: false,
+        aborted_early: false,
+        timed_out: false,
+        interrupted: false,
+    };
+    let mut total_bytes = 0;
+    let mut total_lines = 0;
+
+    let started = Instant::now();
+    let deadline = limits.timeout.map(|timeout| started + timeout);
+    let mut next_check = progress.as_ref().map(|p| started + p.interval);
+
+    loop {
+        let wake_at = [next_check, deadline].into_iter().flatten().fold(Instant::now() + CANCEL_POLL, Instant::min);
+        let received = match rx.recv_timeout(wake_at.saturating_duration_since(Instant::now())) {
+            Ok(received) => received,
+            Err(RecvTimeoutError::Timeout) => {
+                if cancel::requested() {
+                    captured.interrupted = true;
+                    kill_tree(&mut child);
+                    break;
+                }
+
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    captured.timed_out = true;
+                    kill_tree(&mut child);
+                    break;
+                }
+
+                if let Some(progress) = &mut progress
+                    && next_check.is_some_and(|check| Instant::now() >= check) {
+                    if (progress.should_abort)(&captured, started.elapsed()) {
+                        captured.aborted_early = true;
+                        kill_tree(&mut child);
+                        break;
+                    }
+                    next_check = Some(Instant::now() + progress.interval);
+                }
+                continue;
+            },
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let (stream, chunk) = received;
+        if echo {
+            echo_chunk(stream, &chunk);
+        }
+        total_bytes += chunk.len();
+        total_lines += chunk.iter().filter(|b| **b == b'\n').count();
+
+        match stream {
+            Stream::Stdout => captured.stdout.extend_from_slice(&chunk),
+            Stream::Stderr => captured.stderr.extend_from_slice(&chunk),
+        }
+
+        if total_bytes > limits.max_bytes || total_lines > limits.max_lines {
+            captured.runaway = true;
+            kill_tree(&mut child);
+            break;
+        }
+    }
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            captured.timed_out = true;
+            kill_tree(&mut child);
+            break child.wait()?;
+        }
+        if cancel::requested() {
+            captured.interrupted = true;
+            kill_tree(&mut child);
+            break child.wait()?;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    captured.exit_code = status.code();
+    Ok(captured)
+}
+
+fn spawn_reader<R: Read + Send + 'static>(mut source: R, stream: Stream, tx: mpsc::Sender<(Stream, Vec<u8>)>) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match source.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send((stream, buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                },
+            }
+        }
+    });
+}
+
+fn echo_chunk(stream: Stream, chunk: &[u8]) {
+    let _ = match stream {
+        Stream::Stdout => io::stdout().write_all(chunk).and_then(|_| io::stdout().flush()),
+        Stream::Stderr => io::stderr().write_all(chunk),
+    };
+}
+
+fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-KILL", &format!("-{}", child.id())])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_output_is_replaced_with_a_note() {
+        assert_eq!(decode_output("héllo\n".as_bytes()), "héllo\n");
+        assert_eq!(decode_output(&"héllo".as_bytes()[..2]), "h");
+        assert_eq!(decode_output(&[0x89, b'P', b'N', b'G', 0xff, 0x00]), "[binary output, 6 bytes omitted]");
+    }
+}
@@ -0,0 +1,18 @@
+use reqwest::Client;
+
+use crate::api::{send_chat, Message};
+use crate::config::Config;
+
+const EXPLAIN_PROMPT: &str = "You explain shell commands to someone learning git. \
+    Reply with a single plain-English sentence saying what the given command does and what it changes, if anything. \
+    Do not suggest alternatives and do not use the EXECUTE or FINAL protocol.";
+
+pub async fn command(client: &Client, api_key: &str, config: &Config, command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let messages = vec![
+        Message { role: "system".to_string(), content: EXPLAIN_PROMPT.to_string() },
+        Message { role: "user".to_string(), content: command.to_string() },
+    ];
+
+    let (content, _) = send_chat(client, api_key, messages, config).await?;
+    Ok(content.split_whitespace().collect::<Vec<_>>().join(" "))
+}
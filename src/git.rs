@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+pub fn command(work_dir: Option<&Path>) -> Command {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = work_dir {
+        cmd.current_dir(dir);
+    }
+    cmd
+}
+
+fn output_text(mut cmd: Command) -> Option<String> {
+    let output = cmd.output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn output(work_dir: Option<&Path>, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = command(work_dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn ref_exists(name: &str, work_dir: Option<&Path>) -> bool {
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", name)]).stdout(Stdio::null());
+    cmd.status().is_ok_and(|s| s.success())
+}
+
+pub fn is_inside_work_tree(work_dir: Option<&Path>) -> bool {
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--is-inside-work-tree"]).stderr(Stdio::null());
+    output_text(cmd).is_some_and(|inside| inside == "true")
+}
+
+pub fn toplevel(dir: &Path) -> Option<PathBuf> {
+    let mut cmd = command(Some(dir));
+    cmd.args(["rev-parse", "--show-toplevel"]);
+    output_text(cmd).map(PathBuf::from)
+}
+
+// The repository's shared .git directory, the same for every worktree of one repository.
+pub fn common_dir(work_dir: Option<&Path>) -> Option<PathBuf> {
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--path-format=absolute", "--git-common-dir"]);
+    output_text(cmd).and_then(|dir| PathBuf::from(dir).canonicalize().ok())
+}
+
+pub fn head(work_dir: Option<&Path>) -> Option<String> {
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--verify", "--quiet", "HEAD"]);
+    output_text(cmd)
+}
+
+pub fn current_branch(work_dir: Option<&Path>) -> Option<String> {
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--abbrev-ref", "HEAD"]);
+    output_text(cmd).filter(|branch| !branch.is_empty() && branch != "HEAD")
+}
+
+pub fn tracked_file_count(work_dir: Option<&Path>) -> Option<usize> {
+    let mut cmd = command(work_dir);
+    cmd.args(["ls-files", "-z"]).stderr(Stdio::null());
+    let output = cmd.output().ok()?;
+    output.status.success().then(|| output.stdout.iter().filter(|b| **b == 0).count())
+}
+
+pub fn get_default_branch(work_dir: Option<&Path>, configured: Option<&str>) -> Option<String> {
+    static CACHE: Mutex<Option<HashMap<PathBuf, Option<String>>>> = Mutex::new(None);
+
+    let key = toplevel(work_dir.unwrap_or(Path::new(".")))?;
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(branch) = cache.get(&key) {
+        return branch.clone();
+    }
+
+    let branch = resolve_default_branch(work_dir, configured);
+    cache.insert(key, branch.clone());
+    branch
+}
+
+fn resolve_default_branch(work_dir: Option<&Path>, configured: Option<&str>) -> Option<String> {
+    let mut cmd = command(work_dir);
+    cmd.args(["symbolic-ref", "--quiet", "--short", "refs/remotes/origin/HEAD"]);
+    if let Some(branch) = output_text(cmd).filter(|b| !b.is_empty()) {
+        return Some(branch.strip_prefix("origin/").unwrap_or(&branch).to_string());
+    }
+
+    if let Some(branch) = configured.map(str::trim).filter(|b| !b.is_empty()) {
+        return Some(branch.to_string());
+    }
+
+    ["main", "master"].into_iter().map(str::to_string).find(|branch| {
+        let mut cmd = command(work_dir);
+        cmd.args(["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", branch)]);
+        output_text(cmd).is_some()
+    })
+}
+
+pub fn remotes(work_dir: Option<&Path>) -> Vec<String> {
+    let mut cmd = command(work_dir);
+    cmd.arg("remote");
+    output_text(cmd).map(|names| names.lines().map(str::to_string).collect()).unwrap_or_default()
+}
+
+pub fn list_refs(work_dir: Option<&Path>) -> Vec<String> {
+    let mut cmd = command(work_dir);
+    cmd.args(["for-each-ref", "--format=%(refname:short)", "refs/heads", "refs/tags", "refs/remotes"]);
+    output_text(cmd)
+        .map(|refs| refs.lines().filter(|r| !r.ends_with("/HEAD")).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+pub fn resolve_worktree(target: &str, work_dir: Option<&Path>) -> Result<PathBuf, String> {
+    let base = work_dir.map(Path::to_path_buf).unwrap_or_default();
+    let candidate = base.join(target);
+
+    if candidate.is_dir() {
+        let dir = toplevel(&candidate).ok_or_else(|| format!("{} is not inside a git worktree", candidate.display()))?;
+        // Only worktrees of this repository, so the conversation's history and context still apply.
+        let shared = common_dir(Some(&dir));
+        if shared.is_none() || shared != common_dir(work_dir) {
+            return Err(format!("{} belongs to a different repository; /use only switches between worktrees of this one", dir.display()));
+        }
+        return Ok(dir);
+    }
+
+    let mut cmd = command(work_dir);
+    cmd.args(["worktree", "list", "--porcelain"]);
+    let listing = output_text(cmd).ok_or("Could not list git worktrees")?;
+
+    let wanted = format!("refs/heads/{}", target);
+    let mut current: Option<&str> = None;
+
+    for line in listing.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current = Some(path);
+        } else if line.strip_prefix("branch ") == Some(wanted.as_str())
+            && let Some(path) = current {
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    Err(format!("'{}' is neither a directory nor a branch checked out in any worktree", target))
+}
+
+pub fn hooks_for(command_line: &str, work_dir: Option<&Path>) -> Vec<&'static str> {
+    let tokens: Vec<&str> = command_line.split_whitespace().collect();
+    if tokens.first() != Some(&"git") {
+        return Vec::new();
+    }
+
+    let has = |t: &str| tokens.contains(&t);
+    let no_verify = has("--no-verify") || (has("commit") && has("-n"));
+
+    let candidates: &[&'static str] = if has("commit") {
+        if no_verify { &["prepare-commit-msg", "post-commit"] } else { &["pre-commit", "prepare-commit-msg", "commit-msg", "post-commit"] }
+    } else if has("push") {
+        if no_verify { &[] } else { &["pre-push"] }
+    } else if has("merge") || has("pull") {
+        &["pre-merge-commit", "post-merge"]
+    } else if has("rebase") {
+        &["pre-rebase", "post-rewrite"]
+    } else if has("checkout") || has("switch") {
+        &["post-checkout"]
+    } else if has("am") {
+        &["applypatch-msg", "pre-applypatch", "post-applypatch"]
+    } else {
+        &[]
+    };
+
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut cmd = command(work_dir);
+    cmd.args(["rev-parse", "--git-path", "hooks"]);
+    let Some(hooks_path) = output_text(cmd) else { return Vec::new() };
+
+    let mut hooks_dir = PathBuf::from(hooks_path);
+    if hooks_dir.is_relative()
+        && let Some(dir) = work_dir {
+        hooks_dir = dir.join(hooks_dir);
+    }
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|hook| is_executable(&hooks_dir.join(hook)))
+        .collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else { return false };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+pub fn credential_password(host: &str) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    child.stdin.take()?.write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes()).ok()?;
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(|password| password.trim().to_string())
+        .filter(|password| !password.is_empty())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worktrees_of_other_repositories_are_rejected() {
+        let root = std::env::temp_dir().join(format!("jade-worktree-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        let sh = |script: &str| {
+            let status = Command::new("sh").arg("-c").arg(script).current_dir(&root).status().unwrap();
+            assert!(status.success(), "{} failed", script);
+        };
+        sh("git init -q main && git -C main -c user.email=jade@example.com -c user.name=Jade commit -q --allow-empty -m init");
+        sh("git -C main worktree add -q -b topic ../topic");
+        sh("git init -q other");
+
+        let main = root.join("main");
+        let topic = resolve_worktree("../topic", Some(&main));
+        let by_branch = resolve_worktree("topic", Some(&main));
+        let other = resolve_worktree("../other", Some(&main));
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(topic.unwrap().ends_with("topic"));
+        assert!(by_branch.unwrap().ends_with("topic"));
+        assert!(other.unwrap_err().contains("different repository"));
+    }
+}
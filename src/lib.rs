@@ -0,0 +1,113 @@
+pub mod api;
+pub mod audit;
+pub mod cancel;
+pub mod commit;
+pub mod compare;
+pub mod completion;
+pub mod config;
+pub mod context;
+pub mod corrections;
+pub mod credentials;
+pub mod debug;
+pub mod diagnostics;
+pub mod exec;
+pub mod explain;
+pub mod git;
+pub mod output;
+pub mod patch;
+pub mod plan;
+pub mod pr;
+pub mod postprocess;
+pub mod protocol;
+pub mod redact;
+pub mod repl;
+pub mod safety;
+pub mod session;
+pub mod shell;
+pub mod spinner;
+pub mod status;
+pub mod turn;
+pub mod undo;
+pub mod vars;
+
+pub fn progress(config: &config::Config, message: impl std::fmt::Display) {
+    if !config.quiet {
+        println!("{}", console::style(message).dim());
+    }
+}
+
+pub fn truncate_diff(diff: String, max_chars: usize) -> String {
+    if diff.len() <= max_chars {
+        return diff;
+    }
+
+    let mut cut = max_chars;
+    while !diff.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}\n... [diff truncated after {} characters; the file summary above lists every change]", &diff[..cut], max_chars)
+}
+
+// A limit of 0 means no cap, so JADE_FEEDBACK_MAX_CHARS=0 or JADE_FEEDBACK_MAX_LINES=0 turns that limit off.
+pub fn truncate_output(output: &str, max_chars: usize, max_lines: usize) -> String {
+    let max_chars = if max_chars == 0 { usize::MAX } else { max_chars };
+    let max_lines = if max_lines == 0 { usize::MAX } else { max_lines };
+    let lines: Vec<&str> = output.lines().collect();
+    if output.len() <= max_chars && lines.len() <= max_lines {
+        return output.to_string();
+    }
+
+    let (mut head, mut head_chars) = (0, 0);
+    while head < lines.len() && head < max_lines / 2 && head_chars + lines[head].len() < max_chars / 2 {
+        head_chars += lines[head].len() + 1;
+        head += 1;
+    }
+    let (mut tail, mut tail_chars) = (0, 0);
+    while head + tail < lines.len() && tail < max_lines - max_lines / 2 && tail_chars + lines[lines.len() - 1 - tail].len() < max_chars / 2 {
+        tail_chars += lines[lines.len() - 1 - tail].len() + 1;
+        tail += 1;
+    }
+
+    if head == 0 && tail == 0 {
+        let head_end = output.floor_char_boundary(max_chars / 2);
+        let tail_start = output.ceil_char_boundary(output.len().saturating_sub(max_chars / 2));
+        return format!(
+            "{}\n... [{} characters omitted] ...\n{}",
+            &output[..head_end], tail_start - head_end, &output[tail_start..]
+        );
+    }
+
+    format!(
+        "{}\n... [{} lines omitted] ...\n{}",
+        lines[..head].join("\n"), lines.len() - head - tail, lines[lines.len() - tail..].join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_output_keeps_head_and_tail() {
+        let output = (1..=1000).map(|n| format!("line {}", n)).collect::<Vec<_>>().join("\n");
+        let truncated = truncate_output(&output, 100_000, 10);
+        assert_eq!(truncated.lines().next(), Some("line 1"));
+        assert_eq!(truncated.lines().last(), Some("line 1000"));
+        assert!(truncated.contains("... [990 lines omitted] ..."));
+
+        let truncated = truncate_output(&output, 200, 1000);
+        assert!(truncated.len() < 300);
+        assert!(truncated.starts_with("line 1\n") && truncated.ends_with("line 1000"));
+
+        assert_eq!(truncate_output("short", 100, 10), "short");
+        assert!(truncate_output(&"x".repeat(500), 100, 10).contains("[400 characters omitted]"));
+    }
+
+    #[test]
+    fn zero_limits_mean_no_cap() {
+        let output = (1..=20).map(|n| format!("line {}", n)).collect::<Vec<_>>().join("\n");
+        assert_eq!(truncate_output(&output, 100_000, 0), output);
+        assert_eq!(truncate_output(&output, 0, 1000), output);
+        assert!(truncate_output(&output, 0, 4).contains("... [16 lines omitted] ..."));
+    }
+}
@@ -1,43 +1,25 @@
 use console::style;
-use dialoguer::{Confirm, Password};
+use clap::Parser;
+use dialoguer::{Confirm, Password, Select};
 use std::{env, fs, process};
-use std::process::Command;
-use serde::{Deserialize, Serialize};
-use reqwest::Client;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
-use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use rustyline::{EventHandler, KeyEvent};
 
-const SYSTEM_PROMPT: &str = include_str!("prompts/system_prompt.txt");
+mod cli;
 
-const MODEL_NAME: &str = "moonshotai/kimi-k2.5";
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Message {
-    role: String,
-    content: String,
-}
-
-#[derive(Serialize, Debug)]
-struct ChatRequest {
-    model: String,
-    messages: Vec<Message>,
-    stream: bool,
-    temperature: f32,
-    max_tokens: usize,
-}
-
-#[derive(Deserialize, Debug)]
-struct ChatResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: Message,
-}
+use cli::{Cli, Command};
+use jade::completion::{JadeEditor, JadeHelper};
+use jade::api::Message;
+use jade::config::{self, Config};
+use jade::output::{emit, Event, OutputFormat};
+use jade::repl::{repl_step, ClearOrInterrupt, ReplState, StepOutcome, TurnOutcome, TurnStatus};
+use jade::session::SessionStore;
+use jade::turn::run_turn;
+use jade::{cancel, credentials, git, pr};
 
-fn print_welcome() {
+fn print_welcome(model: &str) {
     println!("{}", style("╭──────────────────────────────────────────────────────────────────╮").dim());
 
     println!("{}                                                                  {}", style("│").dim(), style("│").dim());
@@ -82,335 +64,516 @@ fn print_welcome() {
         style("│").dim()
     );
 
+    let model_line: String = format!("Model: {}", model).chars().take(62).collect();
+    let left = (66 - model_line.chars().count()) / 2;
+    let right = 66 - model_line.chars().count() - left;
+    println!(
+        "{}{}{}{}{}",
+        style("│").dim(),
+        " ".repeat(left),
+        style(&model_line).dim(),
+        " ".repeat(right),
+        style("│").dim()
+    );
+
     println!("{}                                                                  {}", style("│").dim(), style("│").dim());
 
     println!("{}", style("╰──────────────────────────────────────────────────────────────────╯").dim());
 }
 
-fn read_user_input(editor: &mut DefaultEditor) -> Result<String, Box<dyn std::error::Error>> {
-    let prompt = format!("{} ", style(">").green().bold());
-
-    match editor.readline(&prompt) {
-        Ok(line) => {
-            let line = line.trim().to_string();
-            if !line.is_empty() {
-                editor.add_history_entry(line.as_str())?;
-            }
-
-            if line == "quit" || line == "exit" {
-                process::exit(0);
-            }
+fn get_env_path(jade_dir: &Path) -> PathBuf {
+    jade_dir.join(".env")
+}
 
-            Ok(line)
-        },
-        Err(ReadlineError::Interrupted) => {
-            println!("Exiting...");
-            process::exit(0);
+fn get_jade_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = match env::var_os("JADE_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .filter(|home| !home.is_empty())
+                .ok_or("Could not determine home directory; set HOME or JADE_DIR")?;
+            PathBuf::from(home).join(".jade")
         },
-        Err(ReadlineError::Eof) => {
-            println!("Exiting...");
-            process::exit(0);
-        },
-        Err(err) => {
-            Err(Box::new(err))
-        }
-    }
+    };
+
+    fs::create_dir_all(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    Ok(path)
 }
 
-fn add_llm_correction(command: &str, correction_message: &str, history: &mut Vec<Message>) {
-    println!("{}", style(format!("LLM correction message: {}", correction_message)).yellow().dim());
+fn setup_editor(jade_dir: &Path, history_size: usize) -> Result<(JadeEditor, PathBuf), Box<dyn std::error::Error>> {
+    // Saving rewrites the whole history file, so this caps it on disk as well as in memory.
+    let editor_config = rustyline::Config::builder().max_history_size(history_size)?.build();
+    let mut editor = JadeEditor::with_config(editor_config)?;
+    editor.set_helper(Some(JadeHelper::default()));
+    editor.bind_sequence(KeyEvent::ctrl('C'), EventHandler::Conditional(Box::new(ClearOrInterrupt)));
 
-    history.push(Message {
-        role: "user".to_string(),
-        content: format!("ERROR: {} command is invalid. {}\nEnsure future queries don't make this mistake again.", command, correction_message),
-    });
-}
+    let history_path = jade_dir.join(".jade_history");
 
-fn get_git_status() -> String {
-    let output = Command::new("git").arg("status").output();
-    match output {
-        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).to_string(),
-        Ok(o) => {
-            let error_msg = String::from_utf8_lossy(&o.stderr).trim().to_string();
-            if error_msg.is_empty() { "Git command failed, no error message.".to_string() } else { error_msg }
-        },
-        Err(e) => format!("Critical Error: Could not execute 'git'. Details: {}", e),
-    }
+    let _ = editor.load_history(&history_path);
+
+    Ok((editor, history_path))
 }
 
-async fn get_llm_response(
-    client: &Client,
-    api_key: &str,
-    user_input: &str,
-    git_status: &str,
-    history: &mut Vec<Message>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let system_msg = Message {
-        role: "system".to_string(),
-        content: format!("{}\n\nGIT STATUS:\n{}", SYSTEM_PROMPT, git_status),
-    };
+// A declined conversation is archived rather than overwritten, so it can still be found later.
+fn resume_session(sessions: &SessionStore) -> Vec<Message> {
+    let saved = sessions.load_current();
+    if saved.is_empty() || !console::user_attended() {
+        return Vec::new();
+    }
 
-    println!("{}", style("Processing...").dim());
+    let resume = Confirm::new()
+        .with_prompt(format!("Resume the previous conversation in this repository ({} messages)?", saved.len()))
+        .default(true)
+        .interact()
+        .unwrap_or(false);
+    if resume {
+        return saved;
+    }
 
-    if !user_input.trim().is_empty() {
-        history.push(Message {
-            role: "user".to_string(),
-            content: user_input.to_string(),
-        });
+    match sessions.archive(&saved) {
+        Ok(id) => println!("{}", style(format!("Archived it as session {}.", id)).dim()),
+        Err(e) => eprintln!("Failed to archive the previous conversation: {}", e),
     }
+    Vec::new()
+}
 
-    let mut request_messages = vec![system_msg];
-    request_messages.extend(history.clone());
+fn setup_config(jade_dir: &Path, heading: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config_file = config::config_path(jade_dir);
 
-    let request_body = ChatRequest {
-        model: MODEL_NAME.to_string(),
-        messages: request_messages,
-        stream: false,
-        temperature: 0.3,
-        max_tokens: 4096,
-    };
+    println!("\n{}", style(heading).yellow().bold());
+    println!("The config file should be at: {}", style(config_file.display()).cyan());
 
-    let res = client.post("https://integrate.api.nvidia.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        return Err(format!("NVIDIA API Error: {}", error_text).into());
+    if !std::io::stdin().is_terminal() {
+        return Err(format!(
+            "stdin is not a terminal, so setup cannot prompt for an API key. \
+            Set NVIDIA_API_KEY in the environment or add api_key = \"<key>\" to {}.",
+            config_file.display()
+        ).into());
     }
 
-    println!("{}", style("Thinking...").dim());
+    let should_setup = Confirm::new()
+        .with_prompt("Would you like to set up your API key now?")
+        .default(true)
+        .interact()?;
 
-    let response_json: ChatResponse = res.json().await?;
-    let raw_text = response_json.choices[0].message.content.clone();
+    if !should_setup {
+        println!("{}", style("Setup cancelled. Please create the config file manually.").yellow());
+        process::exit(1);
+    }
 
-    let cleaned_text = raw_text.replace("`", "").trim().to_string();
+    let api_key = Password::new()
+        .with_prompt("Enter your NVIDIA API key")
+        .interact()?;
 
-    history.push(Message {
-        role: "assistant".to_string(),
-        content: cleaned_text.clone(),
-    });
+    if api_key.trim().is_empty() {
+        println!("{}", style("API key cannot be empty!").red());
+        process::exit(1);
+    }
+
+    match credentials::store_api_key(api_key.trim()) {
+        Ok(()) => {
+            println!("\n{}", style("✓ API key saved to the system keyring!").green().bold());
+            println!("Run {} to remove it.\n", style("jade --logout").cyan());
+        },
+        Err(e) => {
+            println!("{}", style(format!("Could not use the system keyring ({}), saving to the config file instead.", e)).yellow());
+            let saved = config::save_api_key(jade_dir, api_key.trim())?;
 
-    if history.len() > 100 {
-        history.drain(0..2);
+            println!("\n{}", style("✓ Configuration saved successfully!").green().bold());
+            println!("You can edit it later at: {}\n", style(saved.display()).cyan());
+        },
     }
 
-    Ok(cleaned_text)
+    Ok(())
 }
 
-fn handle_execution(command: &str) -> Result<Option<(String, String, bool)>, Box<dyn std::error::Error>> {
-    if command.contains("reset --hard") || command.contains("rm -rf") {
-        return Ok(Some(("Do NOT try to execute any destructive commands".to_string(), "".to_string(), false)));
+fn check_repo_size(config: &Config, work_dir: Option<&Path>) -> bool {
+    if config.max_tracked_files == 0 {
+        return true;
     }
 
-    if command.contains("EXECUTE:") {
-        return Ok(Some((
-            "Each EXECUTE command must be on its own line. Format:\n".to_string() +
-            "EXECUTE: <command>\n" +
-            "...\n" +
-            "EXECUTE: <command>", "".to_string(), false)));
+    let Some(count) = git::tracked_file_count(work_dir) else { return true };
+    if count <= config.max_tracked_files {
+        return true;
     }
 
-    println!("{}", style(format!("Executing command: {}", command)).dim());
+    let cwd = work_dir.map(Path::to_path_buf).or_else(|| env::current_dir().ok()).map(|d| d.display().to_string()).unwrap_or_default();
+    println!(
+        "{}",
+        style(format!(
+            "⚠ {} tracks {} files (limit {}). Gathering status and diffs here may be very slow.",
+            cwd, count, config.max_tracked_files
+        )).yellow().bold()
+    );
+    println!("{}", style("Raise or disable the limit with JADE_MAX_TRACKED_FILES (0 disables the check).").dim());
 
-    let output = if cfg!(target_os = "windows") {
-        Command::new("cmd").args(["/C", command]).output()?
-    } else {
-        Command::new("sh").arg("-c").arg(command).output()?
-    };
+    // --yes answers the question instead of the user, the same as running without a terminal.
+    if config.auto_confirm || !console::user_attended() {
+        return true;
+    }
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    Confirm::new()
+        .with_prompt("Continue in this repository anyway?")
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+}
 
-    if output.status.success() {
-        println!("{}", style("✔ Success").green());
-    } else {
-        println!("{}", style("✖ Failed").red());
-        if !stderr.is_empty() { println!("{}", style(&stderr).red()); }
+fn resolve_directory(dir: &Path) -> Result<PathBuf, String> {
+    match fs::canonicalize(dir) {
+        Ok(resolved) if resolved.is_dir() => Ok(resolved),
+        Ok(_) => Err(format!("Cannot use -C {}: not a directory", dir.display())),
+        Err(e) => Err(format!("Cannot use -C {}: {}", dir.display(), e)),
     }
-
-    Ok(Some((stdout, stderr, true)))
 }
 
-async fn repl_step(
-    client: &Client,
-    api_key: &str,
-    history: &mut Vec<Message>,
-    editor: &mut DefaultEditor,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut current_input = read_user_input(editor)?;
-    let git_status = get_git_status();
-    let mut attempts: i8 = 0;
+fn ensure_git_repo(config: &Config, work_dir: Option<&Path>) -> bool {
+    if git::is_inside_work_tree(work_dir) {
+        return true;
+    }
 
-    println!("{}", style("Understanding user input...").dim());
+    let cwd = work_dir.map(Path::to_path_buf).or_else(|| env::current_dir().ok()).map(|d| d.display().to_string()).unwrap_or_default();
+    eprintln!("{}", style(format!("Jade must be run inside a git repository, and {} is not one.", cwd)).red().bold());
 
-    loop {
-        if attempts > 10 {
-            println!("{}", style("ABORTING: Too many attempts").bold().red());
-            break;
-        }
+    // Never create a repository without being asked to, even under --yes.
+    if config.auto_confirm || !console::user_attended() {
+        return false;
+    }
 
-        let response = get_llm_response(client, api_key, &current_input, &git_status, history).await?;
+    let init = Confirm::new()
+        .with_prompt("Initialize a new git repository here with `git init`?")
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+    if !init {
+        return false;
+    }
 
-        current_input = String::new();
+    match git::output(work_dir, &["init"]) {
+        Ok(output) => {
+            println!("{}", style(output.trim()).dim());
+            true
+        },
+        Err(e) => {
+            eprintln!("{}", style(e).red());
+            false
+        },
+    }
+}
 
-        if response.contains("FINAL:") && response.contains("EXECUTE:") {
-            add_llm_correction(&response, "EXECUTE lines must contain ONLY the command. \
-            Remove all explanations and commentary. Format: `EXECUTE: <command>`.", history);
-        }
+fn write_turn_status(path: &Path, turn: &TurnStatus) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(turn)? + "\n")?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
 
-        if let Some((_, final_msg)) = response.split_once("FINAL:") {
-            let clean_msg = final_msg.trim();
-            if !clean_msg.is_empty() {
-                println!("{}: {}", style("Jade").green().bold(), clean_msg);
-            }
-            break;
-        }
+enum ErrorRecovery {
+    Continue,
+    RetrySetup,
+    Quit,
+}
 
-        let mut executed_something = false;
-        let mut feedback_buffer = String::new();
-
-        for command in response.lines() {
-            if let Some((_, command_cleaned)) = command.trim().split_once("EXECUTE:") {
-                if !command_cleaned.is_empty() {
-                    if let Some((output, error, executed_command)) = handle_execution(command_cleaned)? {
-                        executed_something |= executed_command;
-                        if !executed_command {
-                            add_llm_correction(command_cleaned, &output, history);
-                        } else {
-                            feedback_buffer.push_str(&format!("Output of `{}`:\n{}\n", command_cleaned, output));
-                            if !error.is_empty() {
-                                feedback_buffer.push_str(&format!("ERROR: {}\n", error));
-                            }
-                        }
-                    }
-                }
-            }
-            else {
-                add_llm_correction(command.trim(), "Command should start with `EXECUTE`.", history);
-                continue;
-            }
-        }
+fn ask_error_recovery(consecutive_errors: u32) -> ErrorRecovery {
+    println!(
+        "\n{}",
+        style(format!("The last {} turns failed in a row.", consecutive_errors)).yellow().bold()
+    );
 
-        if executed_something {
-            history.push(Message {
-                role: "user".to_string(),
-                content: feedback_buffer
-            });
-        }
-        else {
-            add_llm_correction(&response, "Command should start with either `FINAL:` or `EXECUTE`.", history);
-        }
+    let choice = Select::new()
+        .with_prompt("What would you like to do?")
+        .items(["Continue", "Re-run setup (API key)", "Quit"])
+        .default(0)
+        .interact();
 
-        attempts += 1;
+    match choice {
+        Ok(0) => ErrorRecovery::Continue,
+        Ok(1) => ErrorRecovery::RetrySetup,
+        _ => ErrorRecovery::Quit,
     }
-    Ok(())
 }
 
-fn get_env_path() -> PathBuf {
-    let home = env::var("HOME")
-        .or_else(|_| env::var("USERPROFILE"))
-        .expect("Could not determine home directory");
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
 
-    let mut path = PathBuf::from(home);
-    path.push(".jade");
+    if cli.no_color || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
 
-    fs::create_dir_all(&path).expect("Failed to create .jade directory");
 
-    path.push(".env");
-    path
-}
+    let jade_dir = match get_jade_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}", style(e).red().bold());
+            process::exit(1);
+        },
+    };
 
-fn get_jade_dir() -> PathBuf {
-    let home = env::var("HOME")
-        .or_else(|_| env::var("USERPROFILE"))
-        .expect("Could not determine home directory");
+    if cli.logout {
+        match credentials::delete_api_key() {
+            Ok(true) => println!("{}", style("✓ Removed the API key from the system keyring.").green()),
+            Ok(false) => println!("{}", style("No API key is stored in the system keyring.").dim()),
+            Err(e) => {
+                eprintln!("{}", style(format!("Could not remove the API key from the system keyring: {}", e)).red().bold());
+                process::exit(1);
+            },
+        }
+        if Config::load(&jade_dir).is_ok_and(|config| config.api_key.is_some()) {
+            println!("An api_key is still set in {}; remove it there too.", style(config::config_path(&jade_dir).display()).cyan());
+        }
+        return;
+    }
 
-    let mut path = PathBuf::from(home);
-    path.push(".jade");
+    let env_file = get_env_path(&jade_dir);
+    let process_key = env::var("NVIDIA_API_KEY").ok().filter(|key| !key.trim().is_empty());
 
-    fs::create_dir_all(&path).expect("Failed to create .jade directory");
-    path
-}
+    if env_file.exists() {
+        dotenvy::from_path(&env_file)
+            .unwrap_or_else(|_| panic!("Failed to load .env from {:?}", env_file));
+    }
 
-fn setup_editor() -> Result<(DefaultEditor, PathBuf), Box<dyn std::error::Error>> {
-    let mut editor = DefaultEditor::new()?;
+    let load_config = || match Config::load(&jade_dir) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", style(format!("Failed to load config: {}", e)).red().bold());
+            process::exit(1);
+        },
+    };
+    let mut config = load_config();
+
+    let mut keyring_key = if config.backend.needs_api_key() && process_key.is_none() { credentials::load_api_key() } else { None };
+    let credential_host = env::var("JADE_CREDENTIAL_HOST").ok().filter(|h| !h.trim().is_empty());
+    let legacy_key = env::var("NVIDIA_API_KEY").ok().filter(|key| !key.trim().is_empty() && process_key.is_none());
+
+    if config.backend.needs_api_key()
+        && process_key.is_none()
+        && keyring_key.is_none()
+        && config.api_key.is_none()
+        && legacy_key.is_none()
+        && credential_host.is_none() {
+        if let Err(e) = setup_config(&jade_dir, "No configuration found!") {
+            eprintln!("{}", style(format!("Setup failed: {}", e)).red().bold());
+            process::exit(1);
+        }
+        config = load_config();
+        keyring_key = credentials::load_api_key();
+    }
 
-    let history_path = get_jade_dir().join(".jade_history");
+    let from_legacy_env = || {
+        let key = legacy_key.clone()?;
+        eprintln!(
+            "{}",
+            style(format!(
+                "Reading the API key from {} is deprecated and will stop working in the next release. Move it to {} as api_key = \"...\".",
+                env_file.display(), config::config_path(&jade_dir).display()
+            )).yellow()
+        );
+        Some(key)
+    };
+    let from_credential_helper = || {
+        let host = credential_host.as_deref()?;
+        let key = git::credential_password(host.trim());
+        if key.is_none() {
+            eprintln!("{}", style(format!("No credential found for {} via git credential helper.", host)).yellow());
+        }
+        key
+    };
 
-    let _ = editor.load_history(&history_path);
+    let api_key = if config.backend.needs_api_key() {
+        process_key.clone().or_else(|| keyring_key.clone()).or_else(|| config.api_key.clone()).or_else(from_legacy_env).or_else(from_credential_helper)
+    } else {
+        Some(String::new())
+    };
+    let mut api_key = match api_key {
+        Some(key) => key,
+        None => {
+            eprintln!(
+                "{}",
+                style(format!("Set api_key in {} or NVIDIA_API_KEY in the environment", config::config_path(&jade_dir).display())).red().bold()
+            );
+            process::exit(1);
+        },
+    };
 
-    Ok((editor, history_path))
-}
+    if let Some(model) = cli.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        config.model = model.to_string();
+    }
+    if let Some(base_url) = cli.base_url.as_deref().map(str::trim).filter(|url| !url.is_empty()) {
+        config.base_url = base_url.trim_end_matches('/').to_string();
+    }
+    if cli.yes {
+        config.auto_confirm = true;
+    }
+    if cli.diff_context_lines.is_some() {
+        config.diff_context_lines = cli.diff_context_lines;
+    }
+    if cli.login_shell {
+        config.login_shell = true;
+    }
+    if cli.dry_run {
+        config.dry_run = true;
+    }
+    if cli.quiet {
+        config.quiet = true;
+    }
+    if cli.format == OutputFormat::Jsonl {
+        config.output_format = OutputFormat::Jsonl;
+        config.quiet = true;
+    }
+    if cli.debug {
+        config.debug_log = Some(jade_dir.join("debug.log"));
+    }
+    if let Some(path) = &config.debug_log {
+        eprintln!("{}", style(format!("Debug log: {}", path.display())).dim());
+    }
+    if cli.git_only {
+        config.git_only = true;
+    }
+    if cli.explain {
+        config.explain = true;
+    }
+    if cli.require_rationale {
+        config.require_rationale = true;
+    }
+    if cli.stream_progress.is_some() {
+        config.stream_progress_secs = cli.stream_progress.filter(|secs| *secs > 0);
+    }
 
-fn setup_config() -> Result<(), Box<dyn std::error::Error>> {
-    let env_file = get_env_path();
+    if cli.no_banner {
+        config.no_banner = true;
+    }
 
-    println!("\n{}", style("No configuration found!").yellow().bold());
-    println!("The config file should be at: {}", style(env_file.display()).cyan());
+    if cli.command.is_none() && cli.prompt.is_none() && !config.no_banner {
+        print_welcome(&config.model);
+    }
 
-    let should_setup = Confirm::new()
-        .with_prompt("Would you like to set up your API key now?")
-        .default(true)
-        .interact()?;
+    let work_dir = match cli.directory.as_deref().map(resolve_directory).transpose() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}", style(e).red().bold());
+            process::exit(1);
+        },
+    };
 
-    if !should_setup {
-        println!("{}", style("Setup cancelled. Please create the .env file manually.").yellow());
+    if !ensure_git_repo(&config, work_dir.as_deref()) {
         process::exit(1);
     }
 
-    let api_key = Password::new()
-        .with_prompt("Enter your NVIDIA API key")
-        .interact()?;
+    let client = match config.http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{}", style(format!("Could not set up the HTTP client: {}", e)).red().bold());
+            process::exit(1);
+        },
+    };
 
-    if api_key.trim().is_empty() {
-        println!("{}", style("API key cannot be empty!").red());
-        process::exit(1);
+    if let Some(command) = &cli.command {
+        let result = match command {
+            Command::PrDescription { base } => pr::describe(&client, &api_key, &config, base.as_deref(), work_dir.as_deref()).await,
+        };
+        match result {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("{}", style(format!("Error: {}", e)).red().bold());
+                process::exit(1);
+            },
+        }
+        return;
     }
 
-    fs::write(&env_file, format!("NVIDIA_API_KEY={}", api_key.trim()))?;
-
-    println!("\n{}", style("✓ Configuration saved successfully!").green().bold());
-    println!("You can edit it later at: {}\n", style(env_file.display()).cyan());
+    if let Some(prompt) = &cli.prompt {
+        let mut state = ReplState { work_dir: work_dir.clone(), ..Default::default() };
+        if let Err(e) = run_turn(&client, &api_key, &mut state, &config, &config.executor(), None, prompt.clone()).await {
+            emit(&config, Event::Error { message: &format!("Error: {}", e) });
+            state.turn().outcome = TurnOutcome::Error;
+        }
 
-    Ok(())
-}
+        let turn = state.turn();
+        if let Some(path) = &cli.status_file
+            && let Err(e) = write_turn_status(path, turn) {
+            eprintln!("Failed to write status file: {}", e);
+        }
+        process::exit(if matches!(turn.outcome, TurnOutcome::Success) { 0 } else { 1 });
+    }
 
-#[tokio::main]
-async fn main() {
-    print_welcome();
-    let client = Client::new();
+    let (mut editor, history_path) = setup_editor(&jade_dir, config.history_size)
+        .expect("Failed to initialize terminal editor");
 
-    let env_file = get_env_path();
+    if let Some(suggestion) = &config.suggestion {
+        println!("{}\n", style(suggestion).dim().italic());
+    }
 
-    if !env_file.exists() {
-        if let Err(e) = setup_config() {
-            eprintln!("{}", style(format!("Setup failed: {}", e)).red().bold());
-            process::exit(1);
-        }
+    if !check_repo_size(&config, work_dir.as_deref()) {
+        process::exit(0);
     }
 
-    dotenvy::from_path(&env_file)
-        .expect(&format!("Failed to load .env from {:?}", env_file));
+    let repo_root = git::toplevel(work_dir.as_deref().unwrap_or(Path::new("."))).unwrap_or_else(|| PathBuf::from("."));
+    let sessions = SessionStore::new(jade_dir.clone(), &repo_root, config.autosave_interval(), config.session_max_bytes);
+    let executor = config.executor();
+    cancel::listen();
+    let mut state = ReplState {
+        history: if cli.fresh { Vec::new() } else { resume_session(&sessions) },
+        work_dir,
+        ..Default::default()
+    };
 
-    let api_key = env::var("NVIDIA_API_KEY")
-        .expect("NVIDIA_API_KEY must be set in .env file");
+    let mut consecutive_errors: u32 = 0;
 
-    let (mut editor, history_path) = setup_editor()
-        .expect("Failed to initialize terminal editor");
+    loop {
+        let mut quitting = false;
+        match repl_step(&client, &api_key, &mut state, &mut editor, &sessions, &mut config, &executor).await {
+            Ok(StepOutcome::Continue) => consecutive_errors = 0,
+            Ok(StepOutcome::Quit) => quitting = true,
+            Err(e) => {
+                println!("{}", style(format!("Critical Error: {}", e)).red().bold());
+                consecutive_errors += 1;
+                if let Some(turn) = state.turn.as_mut() {
+                    turn.outcome = TurnOutcome::Error;
+                }
+            },
+        }
 
-    let mut history: Vec<Message> = Vec::new();
+        if let Some(turn) = state.turn.take()
+            && let Some(path) = &cli.status_file
+            && let Err(e) = write_turn_status(path, &turn) {
+            eprintln!("Failed to write status file: {}", e);
+        }
 
-    loop {
-        if let Err(e) = repl_step(&client, &api_key, &mut history, &mut editor).await {
-            println!("{}", style(format!("Critical Error: {}", e)).red().bold());
+        if !cli.resume_on_error
+            && config.max_consecutive_errors > 0
+            && consecutive_errors >= config.max_consecutive_errors {
+            match ask_error_recovery(consecutive_errors) {
+                ErrorRecovery::Continue => {},
+                ErrorRecovery::RetrySetup => {
+                    if let Err(e) = setup_config(&jade_dir, "Re-running setup") {
+                        eprintln!("{}", style(format!("Setup failed: {}", e)).red().bold());
+                    } else if let Some(key) = credentials::load_api_key()
+                        .or_else(|| Config::load(&jade_dir).ok().and_then(|reloaded| reloaded.api_key)) {
+                        api_key = key;
+                    }
+                },
+                ErrorRecovery::Quit => {
+                    emit(&config, Event::SessionSummary { commands: &state.executed });
+                    process::exit(1);
+                },
+            }
+            consecutive_errors = 0;
         }
 
         if let Err(e) = editor.save_history(&history_path) {
             eprintln!("Failed to save history: {}", e);
         }
+
+        if let Err(e) = sessions.save_current(&state.history) {
+            eprintln!("Failed to save session: {}", e);
+        }
+
+        if quitting {
+            emit(&config, Event::SessionSummary { commands: &state.executed });
+            break;
+        }
     }
-}
\ No newline at end of file
+}
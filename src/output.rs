@@ -0,0 +1,86 @@
+use console::style;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::progress;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Jsonl,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    Prompt { text: &'a str },
+    CommandExecuted { command: &'a str },
+    CommandOutput { command: &'a str, stdout: &'a str, stderr: &'a str, exit_code: Option<i32>, success: bool },
+    FinalAnswer { text: &'a str },
+    Error { message: &'a str },
+    // Printed when Jade exits, listing every command the session ran.
+    SessionSummary { commands: &'a [String] },
+}
+
+pub fn emit(config: &Config, event: Event) {
+    if config.output_format == OutputFormat::Jsonl {
+        match serde_json::to_string(&event) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("Failed to encode event: {}", e),
+        }
+        return;
+    }
+
+    match event {
+        Event::Prompt { .. } => {},
+        Event::CommandExecuted { command } => progress(config, format!("Executing command: {}", command)),
+        Event::CommandOutput { success: true, .. } => println!("{}", style("✔ Success").green()),
+        Event::CommandOutput { stderr, .. } => {
+            println!("{}", style("✖ Failed").red());
+            // Without quiet the command's own output was already streamed as it ran.
+            if config.quiet && !stderr.is_empty() { println!("{}", style(stderr).red()); }
+        },
+        Event::FinalAnswer { text } => {
+            if !text.is_empty() {
+                println!("{}: {}", style("Jade").green().bold(), text);
+            }
+        },
+        Event::Error { message } => eprintln!("{}", style(message).red().bold()),
+        Event::SessionSummary { commands: [] } => println!("{}", style("This session ran no commands.").dim()),
+        Event::SessionSummary { commands } => {
+            println!("{}", style(format!("This session ran {} command(s):", commands.len())).bold());
+            for command in commands {
+                println!("  {}", style(command).cyan());
+            }
+        },
+    }
+}
+
+// Human-facing messages go to stderr in JSONL mode so stdout stays one event per line.
+pub fn notice(config: &Config, message: impl std::fmt::Display) {
+    match config.output_format {
+        OutputFormat::Human => println!("{}", message),
+        OutputFormat::Jsonl => eprintln!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_as_tagged_lines() {
+        let event = Event::CommandOutput { command: "git status", stdout: "clean\n", stderr: "", exit_code: Some(0), success: true };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"command-output","command":"git status","stdout":"clean\n","stderr":"","exit_code":0,"success":true}"#
+        );
+        assert_eq!(serde_json::to_string(&Event::FinalAnswer { text: "done" }).unwrap(), r#"{"event":"final-answer","text":"done"}"#);
+        let commands = vec!["git add -A".to_string()];
+        assert_eq!(
+            serde_json::to_string(&Event::SessionSummary { commands: &commands }).unwrap(),
+            r#"{"event":"session-summary","commands":["git add -A"]}"#
+        );
+    }
+}
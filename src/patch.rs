@@ -0,0 +1,98 @@
+use crate::shell;
+
+// Global git options that take their value as the next argument, e.g. `git -C dir apply x.patch`.
+const GLOBAL_VALUE_OPTIONS: &[&str] = &["-C", "-c", "--git-dir", "--work-tree", "--namespace"];
+
+// `git apply` and `git am` options whose value can be the next argument, so it is not mistaken for a patch file.
+const VALUE_OPTIONS: &[&str] = &[
+    "-p", "-C", "--directory", "--exclude", "--include", "--whitespace", "--patch-format", "--resolvemsg", "--build-fake-ancestor",
+];
+
+pub fn check_command(command: &str) -> Option<String> {
+    let args = shell::split(command)?;
+    if args.first().map(String::as_str) != Some("git") {
+        return None;
+    }
+
+    let position = subcommand_position(&args)?;
+    if args[position] != "apply" && args[position] != "am" {
+        return None;
+    }
+    let rest = &args[position + 1..];
+
+    if rest.iter().any(|a| a == "--check" || a == "--abort" || a == "--continue" || a == "--skip") {
+        return None;
+    }
+
+    let (options, patches) = split_patch_args(rest);
+    if patches.is_empty() {
+        return None;
+    }
+
+    let mut check = args[..position].to_vec();
+    check.extend(["apply".to_string(), "--check".to_string()]);
+    if args[position] == "apply" {
+        check.extend_from_slice(rest);
+    } else {
+        // Only the options `git apply` shares with `git am` carry over, like --directory.
+        check.extend(options.into_iter().filter(|o| is_apply_option(o)));
+        check.extend(patches.into_iter().cloned());
+    }
+    Some(shell::join(&check))
+}
+
+fn subcommand_position(args: &[String]) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if !arg.starts_with('-') {
+            return Some(i);
+        }
+        i += if GLOBAL_VALUE_OPTIONS.contains(&arg) { 2 } else { 1 };
+    }
+    None
+}
+
+// Splits the arguments after `apply`/`am` into options (a separate option value joined to its option with `=`) and
+// patch files.
+fn split_patch_args(args: &[String]) -> (Vec<String>, Vec<&String>) {
+    let (mut options, mut patches) = (Vec::new(), Vec::new());
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with('-') {
+            patches.push(arg);
+        } else if VALUE_OPTIONS.contains(&arg.as_str()) && let Some(value) = iter.next() {
+            options.push(if arg.starts_with("--") { format!("{}={}", arg, value) } else { format!("{}{}", arg, value) });
+        } else {
+            options.push(arg.clone());
+        }
+    }
+    (options, patches)
+}
+
+fn is_apply_option(option: &str) -> bool {
+    let name = option.split('=').next().unwrap_or(option);
+    ["--directory", "--exclude", "--include", "--whitespace"].contains(&name)
+        || (option.len() > 2 && (option.starts_with("-p") || option.starts_with("-C")) && !option.starts_with("--"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_patches_before_applying_them() {
+        assert_eq!(check_command("git apply fix.patch").as_deref(), Some("git apply --check fix.patch"));
+        assert_eq!(check_command("git -C dir apply x.patch").as_deref(), Some("git -C dir apply --check x.patch"));
+        assert_eq!(check_command("git am --directory d x.patch").as_deref(), Some("git apply --check --directory=d x.patch"));
+        assert_eq!(check_command("git -c core.autocrlf=false am x.patch").as_deref(), Some("git -c core.autocrlf=false apply --check x.patch"));
+    }
+
+    #[test]
+    fn leaves_other_commands_alone() {
+        assert_eq!(check_command("git stash apply 1"), None);
+        assert_eq!(check_command("git apply --check fix.patch"), None);
+        assert_eq!(check_command("git am --continue"), None);
+        assert_eq!(check_command("git apply --directory d"), None);
+    }
+}
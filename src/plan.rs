@@ -0,0 +1,122 @@
+use console::style;
+use dialoguer::{Confirm, Editor};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set: Vec<String>,
+    #[serde(default)]
+    pub commands: Vec<String>,
+    #[serde(rename = "final", default)]
+    pub final_message: Option<String>,
+}
+
+impl Plan {
+    pub fn from_response(response: &str) -> Plan {
+        let mut plan = Plan { set: Vec::new(), commands: Vec::new(), final_message: None };
+
+        for line in response.lines().map(str::trim) {
+            if let Some(assignment) = line.strip_prefix("SET:") {
+                plan.set.push(assignment.trim().to_string());
+            } else if let Some((_, command)) = line.split_once("EXECUTE:") {
+                plan.commands.push(command.trim().to_string());
+            }
+        }
+
+        if let Some((_, final_message)) = response.split_once("FINAL:") {
+            plan.final_message = Some(final_message.trim().to_string());
+        }
+
+        plan
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let single_line = |entry: &String| !entry.trim().is_empty() && !entry.contains('\n');
+
+        if !self.set.iter().all(|s| single_line(s) && s.contains('=')) {
+            return Err("every \"set\" entry must be a single `NAME=value` line".to_string());
+        }
+        if !self.commands.iter().all(single_line) {
+            return Err("every entry in \"commands\" must be a non-empty, single-line command".to_string());
+        }
+        if self.commands.iter().any(|c| c.contains("EXECUTE:") || c.contains("FINAL:")) {
+            return Err("commands must not contain EXECUTE: or FINAL: themselves".to_string());
+        }
+
+        match &self.final_message {
+            Some(_) if !self.commands.is_empty() || !self.set.is_empty() => {
+                Err("a plan has either \"commands\" or \"final\", not both".to_string())
+            },
+            Some(message) if message.trim().is_empty() => Err("\"final\" must not be empty".to_string()),
+            None if self.commands.is_empty() && self.set.is_empty() => {
+                Err("the plan needs at least one command or a \"final\" message".to_string())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn to_response(&self) -> String {
+        if let Some(message) = &self.final_message {
+            return format!("FINAL: {}", message.trim());
+        }
+
+        let sets = self.set.iter().map(|s| format!("SET: {}", s.trim()));
+        let commands = self.commands.iter().map(|c| format!("EXECUTE: {}", c.trim()));
+        sets.chain(commands).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn edit(&self) -> Result<Option<Plan>, Box<dyn std::error::Error>> {
+        let mut text = serde_json::to_string_pretty(self)?;
+
+        loop {
+            let Some(edited) = Editor::new().extension(".json").edit(&text)? else { return Ok(None) };
+
+            match serde_json::from_str::<Plan>(&edited).map_err(|e| e.to_string()).and_then(|plan| plan.validate().map(|_| plan)) {
+                Ok(plan) => return Ok(Some(plan)),
+                Err(e) => {
+                    println!("{}", style(format!("Invalid plan: {}", e)).red());
+                    if !Confirm::new().with_prompt("Edit it again?").default(true).interact()? {
+                        return Ok(None);
+                    }
+                    text = edited;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_response() {
+        let response = "SET: BRANCH=feature/x\nEXECUTE: git checkout -b {{BRANCH}}\nEXECUTE: git status";
+        let plan = Plan::from_response(response);
+        assert_eq!(plan.commands, vec!["git checkout -b {{BRANCH}}", "git status"]);
+        assert_eq!(plan.to_response(), response);
+    }
+
+    #[test]
+    fn rejects_invalid_edits() {
+        assert!(serde_json::from_str::<Plan>(r#"{"commands": ["git status"], "extra": 1}"#).is_err());
+
+        let invalid = [
+            r#"{"commands": []}"#,
+            r#"{"commands": ["git status"], "final": "done"}"#,
+            r#"{"commands": ["git add .\ngit commit"]}"#,
+            r#"{"commands": ["EXECUTE: git status"]}"#,
+            r#"{"set": ["NO_EQUALS"], "commands": ["git status"]}"#,
+        ];
+        for json in invalid {
+            let plan: Plan = serde_json::from_str(json).unwrap();
+            assert!(plan.validate().is_err(), "{} should be rejected", json);
+        }
+
+        let plan: Plan = serde_json::from_str(r#"{"final": "Nothing to do."}"#).unwrap();
+        assert_eq!(plan.validate(), Ok(()));
+        assert_eq!(plan.to_response(), "FINAL: Nothing to do.");
+    }
+}
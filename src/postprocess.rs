@@ -0,0 +1,44 @@
+use console::style;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::config::Config;
+use crate::output::notice;
+
+pub fn apply_filter(config: &Config, response: &str, filter: &str) -> String {
+    match run_filter(response, filter) {
+        Ok(filtered) => filtered,
+        Err(e) => {
+            notice(config, style(format!("Response filter `{}` failed, using the unfiltered response: {}", filter, e)).yellow());
+            response.to_string()
+        },
+    }
+}
+
+fn run_filter(response: &str, filter: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut child = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", filter])
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("sh").arg("-c").arg(filter)
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+            .spawn()?
+    };
+
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = response.to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
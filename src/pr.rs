@@ -0,0 +1,62 @@
+use console::style;
+use reqwest::Client;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::api::{send_chat, Message};
+use crate::{git, truncate_diff};
+
+const PR_PROMPT: &str = include_str!("prompts/pr_description.txt");
+const MAX_PR_DIFF_CHARS: usize = 24_000;
+
+pub async fn describe(
+    client: &Client,
+    api_key: &str,
+    config: &Config,
+    base: Option<&str>,
+    work_dir: Option<&Path>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => {
+            let branch = git::get_default_branch(work_dir, config.default_branch.as_deref())
+                .ok_or("Could not determine the default branch (no origin/HEAD, main or master), pass the base branch explicitly")?;
+            let remote = format!("origin/{}", branch);
+            if !git::ref_exists(&branch, work_dir) && git::ref_exists(&remote, work_dir) { remote } else { branch }
+        },
+    };
+    let head = git::current_branch(work_dir).unwrap_or_else(|| "HEAD".to_string());
+
+    let log = git::output(work_dir, &["log", "--oneline", "--no-decorate", &format!("{}..HEAD", base)])?;
+    if log.trim().is_empty() {
+        return Err(format!("{} has no commits that are not already in {}", head, base).into());
+    }
+
+    let mut diff_args = vec!["--no-pager".to_string(), "diff".to_string(), format!("{}...HEAD", base)];
+    if let Some(lines) = config.diff_context_lines {
+        diff_args.push(format!("-U{}", lines));
+    }
+    if !config.diff_exclude.is_empty() {
+        diff_args.push("--".to_string());
+        diff_args.extend(config.diff_exclude.iter().map(|glob| format!(":(exclude){}", glob)));
+    }
+    let diff_args: Vec<&str> = diff_args.iter().map(String::as_str).collect();
+    let stat = git::output(work_dir, &["--no-pager", "diff", "--stat", &format!("{}...HEAD", base)])?;
+    let diff = truncate_diff(git::output(work_dir, &diff_args)?, MAX_PR_DIFF_CHARS);
+
+    eprintln!("{}", style(format!("Describing {} against {}...", head, base)).dim());
+
+    let messages = vec![
+        Message { role: "system".to_string(), content: PR_PROMPT.to_string() },
+        Message {
+            role: "user".to_string(),
+            content: config.redactor.redact(&format!(
+                "BRANCH: {}\nBASE: {}\n\nCOMMITS:\n{}\n\nDIFF STAT:\n{}\n\nDIFF:\n{}",
+                head, base, log.trim(), stat.trim(), diff,
+            )),
+        },
+    ];
+
+    let (content, _) = send_chat(client, api_key, messages, config).await?;
+    Ok(content.trim().to_string())
+}
@@ -0,0 +1,130 @@
+use crate::corrections::Violation;
+
+#[derive(Debug, PartialEq)]
+pub enum Line {
+    Why(String),
+    Set(String),
+    Execute(String),
+    Unprefixed(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParsedResponse {
+    Commands(Vec<Line>),
+    Final(String),
+    Malformed { reason: String, violation: Violation },
+}
+
+pub fn planned_commands(lines: &[Line]) -> Vec<&str> {
+    lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Execute(command) if !command.is_empty() => Some(command.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn parse_response(raw: &str) -> ParsedResponse {
+    let mut lines = Vec::new();
+    let mut rest = raw.lines();
+
+    while let Some(line) = rest.next() {
+        let line = line.trim();
+
+        if let Some(message) = line.strip_prefix("FINAL:") {
+            let message = std::iter::once(message).chain(rest).collect::<Vec<_>>().join("\n");
+            let mixed = lines.iter().any(|l| matches!(l, Line::Execute(_) | Line::Set(_)))
+                || message.lines().any(|l| l.trim_start().starts_with("EXECUTE:") || l.trim_start().starts_with("SET:"));
+            if mixed {
+                return ParsedResponse::Malformed {
+                    reason: "FINAL must appear alone. Either run commands with EXECUTE lines and wait for their output, \
+                        or reply with only `FINAL: <message>`.".to_string(),
+                    violation: Violation::MixedFinalExecute,
+                };
+            }
+            return ParsedResponse::Final(message.trim().to_string());
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        lines.push(if let Some(reason) = line.strip_prefix("WHY:") {
+            Line::Why(reason.trim().to_string())
+        } else if let Some(assignment) = line.strip_prefix("SET:") {
+            Line::Set(assignment.trim().to_string())
+        } else if let Some(command) = execute_command(line) {
+            Line::Execute(command.to_string())
+        } else {
+            Line::Unprefixed(line.to_string())
+        });
+    }
+
+    if !lines.iter().any(|l| matches!(l, Line::Execute(_) | Line::Set(_))) {
+        return ParsedResponse::Malformed {
+            reason: "Command should start with either `FINAL:` or `EXECUTE`.".to_string(),
+            violation: Violation::NoProtocol,
+        };
+    }
+
+    ParsedResponse::Commands(lines)
+}
+
+fn execute_command(line: &str) -> Option<&str> {
+    line.trim().split_once("EXECUTE:").map(|(_, command)| command.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_execute_lines_have_no_command() {
+        assert_eq!(execute_command("EXECUTE:"), Some(""));
+        assert_eq!(execute_command("EXECUTE:    "), Some(""));
+        assert_eq!(execute_command("  EXECUTE: \t "), Some(""));
+        assert_eq!(execute_command("FINAL: done"), None);
+    }
+
+    #[test]
+    fn execute_commands_are_trimmed() {
+        assert_eq!(execute_command("EXECUTE:   git status   "), Some("git status"));
+        assert_eq!(execute_command("\tEXECUTE: git log --oneline -n 5\t"), Some("git log --oneline -n 5"));
+        assert_eq!(execute_command("EXECUTE:git add -A"), Some("git add -A"));
+    }
+
+    #[test]
+    fn parses_commands_in_order() {
+        let parsed = parse_response("  WHY: check state  \n\nSET: BASE=main\nEXECUTE: git status   \n\n");
+        assert_eq!(parsed, ParsedResponse::Commands(vec![
+            Line::Why("check state".to_string()),
+            Line::Set("BASE=main".to_string()),
+            Line::Execute("git status".to_string()),
+        ]));
+        if let ParsedResponse::Commands(lines) = &parsed {
+            assert_eq!(planned_commands(lines), vec!["git status"]);
+        }
+    }
+
+    #[test]
+    fn final_messages_keep_every_line() {
+        let parsed = parse_response("\n  FINAL: Drafted the description:\n\n- mentions EXECUTE: in passing\n");
+        assert_eq!(parsed, ParsedResponse::Final("Drafted the description:\n\n- mentions EXECUTE: in passing".to_string()));
+    }
+
+    #[test]
+    fn mixed_and_empty_responses_are_malformed() {
+        let violation = |raw| match parse_response(raw) {
+            ParsedResponse::Malformed { violation, .. } => Some(violation),
+            _ => None,
+        };
+
+        assert_eq!(violation("EXECUTE: git add .\nFINAL: done"), Some(Violation::MixedFinalExecute));
+        assert_eq!(violation("FINAL: done\nEXECUTE: git push"), Some(Violation::MixedFinalExecute));
+        assert_eq!(violation(""), Some(Violation::NoProtocol));
+        assert_eq!(violation("   \n\t\n"), Some(Violation::NoProtocol));
+        assert_eq!(violation("Sure, I can help with that."), Some(Violation::NoProtocol));
+        assert_eq!(violation("EXECUTE: git status\nsome commentary"), None);
+    }
+}
@@ -0,0 +1,420 @@
+use console::style;
+use dialoguer::Confirm;
+use reqwest::Client;
+use rustyline::error::ReadlineError;
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, Movement, RepeatCount};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::api::{Message, Usage};
+use crate::completion::JadeEditor;
+use crate::config::{self, Config};
+use crate::corrections::Violation;
+use crate::exec::CommandExecutor;
+use crate::session::SessionStore;
+use crate::turn::{get_git_status, handle_execution, run_turn, ExecutionOutcome, ProgressWatch};
+use crate::undo::{self, ExecutedCommand};
+use crate::vars::Variables;
+use crate::{cancel, commit, compare, git, shell};
+
+#[derive(Default)]
+pub struct ReplState {
+    pub history: Vec<Message>,
+    pub last_raw_response: Option<String>,
+    pub variables: Variables,
+    pub work_dir: Option<PathBuf>,
+    pub last_context: Option<String>,
+    pub review_plans: bool,
+    pub turn: Option<TurnStatus>,
+    pub last_executed: Option<ExecutedCommand>,
+    pub executed: Vec<String>,
+    // Tokens the conversation's own requests have used, for the running total after each turn.
+    pub usage: Usage,
+}
+
+impl ReplState {
+    pub fn turn(&mut self) -> &mut TurnStatus {
+        self.turn.get_or_insert_default()
+    }
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TurnOutcome {
+    #[default]
+    Success,
+    Aborted,
+    Error,
+}
+
+#[derive(Serialize, Default)]
+pub struct TurnStatus {
+    pub outcome: TurnOutcome,
+    pub commands_run: u32,
+    pub commands_rejected: u32,
+    pub any_rejected: bool,
+}
+
+impl TurnStatus {
+    pub fn record_rejection(&mut self, count: usize) {
+        self.commands_rejected += count as u32;
+        self.any_rejected = true;
+    }
+}
+
+pub enum UserInput {
+    Prompt(String),
+    Command(String),
+    Quit,
+}
+
+pub enum StepOutcome {
+    Continue,
+    Quit,
+}
+
+// Ctrl-C clears a half-typed line and only exits Jade at an empty prompt.
+pub struct ClearOrInterrupt;
+
+impl ConditionalEventHandler for ClearOrInterrupt {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        (!ctx.line().is_empty()).then_some(Cmd::Kill(Movement::WholeLine))
+    }
+}
+
+const MULTILINE_DELIMITER: &str = "\"\"\"";
+const HISTORY_SUMMARY_CHARS: usize = 80;
+
+pub fn read_multiline_block(editor: &mut JadeEditor) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    println!("{}", style(format!("Multi-line input: finish with {} on its own line.", MULTILINE_DELIMITER)).dim());
+
+    let mut lines = Vec::new();
+    loop {
+        match editor.readline(&format!("{} ", style("…").green())) {
+            Ok(line) if line.trim() == MULTILINE_DELIMITER => break,
+            Ok(line) => lines.push(line),
+            Err(ReadlineError::Eof) => break,
+            Err(ReadlineError::Interrupted) => return Ok(None),
+            Err(err) => return Err(Box::new(err)),
+        }
+    }
+
+    let block = lines.join("\n").trim().to_string();
+    if block.is_empty() {
+        return Ok(None);
+    }
+
+    let collapsed = block.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut summary: String = collapsed.chars().take(HISTORY_SUMMARY_CHARS).collect();
+    if collapsed.chars().count() > HISTORY_SUMMARY_CHARS {
+        summary.push('…');
+    }
+    editor.add_history_entry(format!("{} [{} lines]", summary, lines.len()))?;
+
+    Ok(Some(block))
+}
+
+pub fn read_user_input(editor: &mut JadeEditor, config: &Config) -> Result<UserInput, Box<dyn std::error::Error>> {
+    let prompt = match &config.prompt {
+        Some(prompt) => prompt.clone(),
+        None => format!("{} ", style(">").green().bold()),
+    };
+
+    match editor.readline(&prompt) {
+        Ok(line) if line.trim() == MULTILINE_DELIMITER => match read_multiline_block(editor)? {
+            Some(block) => Ok(UserInput::Prompt(block)),
+            None => {
+                println!("{}", style("Multi-line input cancelled.").dim());
+                read_user_input(editor, config)
+            },
+        },
+        Ok(line) => {
+            let line = line.trim().to_string();
+            if !line.is_empty() {
+                editor.add_history_entry(line.as_str())?;
+            }
+
+            if line == "quit" || line == "exit" {
+                return Ok(UserInput::Quit);
+            }
+
+            if line.starts_with('/') {
+                Ok(UserInput::Command(line))
+            } else {
+                Ok(UserInput::Prompt(line))
+            }
+        },
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
+            println!("Exiting...");
+            Ok(UserInput::Quit)
+        },
+        Err(err) => {
+            Err(Box::new(err))
+        }
+    }
+}
+
+pub async fn handle_slash_command(
+    line: &str,
+    state: &mut ReplState,
+    sessions: &SessionStore,
+    client: &Client,
+    api_key: &str,
+    config: &mut Config,
+    executor: &dyn CommandExecutor,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = line.split_whitespace().next().unwrap_or(line);
+
+    match command {
+        "/new" => {
+            if state.history.is_empty() {
+                println!("{}", style("Nothing to archive, already on a fresh task.").dim());
+                return Ok(());
+            }
+
+            let id = sessions.archive(&state.history)?;
+            state.history.clear();
+            state.variables.clear();
+            state.usage = Usage::default();
+            println!("{}", style(format!("✓ Archived previous task as session {}. Starting fresh.", id)).green());
+        },
+        "/clear" => {
+            state.history.clear();
+            state.variables.clear();
+            state.usage = Usage::default();
+            println!("{}", style("✓ Conversation cleared.").green());
+        },
+        "/reset" => {
+            let confirmed = console::user_attended()
+                && Confirm::new()
+                    .with_prompt("Forget this conversation, its variables and the saved session? This cannot be undone")
+                    .default(false)
+                    .interact()?;
+            if !confirmed {
+                println!("{}", style("Nothing was reset.").dim());
+                return Ok(());
+            }
+
+            state.history.clear();
+            state.variables.clear();
+            state.usage = Usage::default();
+            state.last_executed = None;
+            sessions.delete_current()?;
+            println!("{}", style("✓ Conversation and saved session deleted.").green());
+        },
+        "/history" => {
+            if state.history.is_empty() {
+                println!("{}", style("No messages in this conversation yet.").dim());
+            }
+            for message in &state.history {
+                let role = match message.role.as_str() {
+                    "user" => style("You").cyan().bold(),
+                    "assistant" => style("Jade").green().bold(),
+                    other => style(other).dim().bold(),
+                };
+                println!("{}: {}", role, message.content.trim());
+            }
+        },
+        "/model" => {
+            let name = line["/model".len()..].trim();
+            if name.is_empty() {
+                println!("Active model: {}", style(&config.model).cyan());
+                return Ok(());
+            }
+
+            config.model = name.to_string();
+            println!("{}", style(format!("✓ Using {} for the next requests.", name)).green());
+            match config::save_model(&config.config_file, name) {
+                Ok(()) if std::env::var_os("JADE_MODEL").is_some() => println!(
+                    "{}", style(format!("Saved to {}, but JADE_MODEL overrides it at startup.", config.config_file.display())).yellow()
+                ),
+                Ok(()) => println!("{}", style(format!("Saved as the default in {}.", config.config_file.display())).dim()),
+                Err(e) => println!("{}", style(format!("Could not save the model choice: {}", e)).yellow()),
+            }
+        },
+        "/temp" => {
+            let value = line["/temp".len()..].trim();
+            if value.is_empty() {
+                println!("Temperature: {}", style(config.params_for(&config.model).temperature).cyan());
+                return Ok(());
+            }
+
+            match value.parse::<f32>() {
+                Ok(temperature) if (0.0..=2.0).contains(&temperature) => {
+                    config.param_overrides.temperature = Some(temperature);
+                    println!("{}", style(format!("✓ Using temperature {} for the next requests.", temperature)).green());
+                },
+                _ => println!("{}", style("Usage: /temp <0.0-2.0>").yellow()),
+            }
+        },
+        "/help" => {
+            let commands = [
+                ("/help", "Show this list"),
+                ("/new", "Archive this conversation and start a fresh task"),
+                ("/clear", "Forget this conversation without archiving it"),
+                ("/reset", "Forget this conversation and delete the saved session, after confirming"),
+                ("/history", "Print the messages in this conversation"),
+                ("/model [name]", "Show or switch the model used for the next requests"),
+                ("/temp [value]", "Show or set the sampling temperature for the next requests"),
+                ("/commit", "Draft a commit message for the staged changes and commit"),
+                ("/undo", "Reverse the last command that changed the repository, after confirming"),
+                ("/status", "Show git status"),
+                ("/context", "Show the repository context sent with the last request"),
+                ("/raw", "Print the raw body of the last API response"),
+                ("/plan", "Toggle editing each proposed plan as JSON before it runs"),
+                ("/use [path|branch]", "Show or switch the worktree Jade operates on"),
+                ("/compare <a> <b>", "Explain how two branches diverged"),
+                ("quit, exit", "Leave Jade"),
+            ];
+            for (name, description) in commands {
+                println!("  {:<20} {}", style(name).cyan(), description);
+            }
+        },
+        "/raw" => match &state.last_raw_response {
+            Some(raw) => {
+                println!("{}", style(format!("Raw body of the last API response ({} bytes of streamed JSON events, may be large):", raw.len())).dim());
+                println!("{}", raw);
+            },
+            None => println!("{}", style("No API response received yet in this session.").dim()),
+        },
+        "/context" => match &state.last_context {
+            Some(context) => {
+                println!("{}", style("Context sent with the last request:").bold());
+                println!("{}", context);
+                println!("\n{}", style("Current status:").bold());
+                print!("{}", get_git_status(true, state.work_dir.as_deref()));
+            },
+            None => println!("{}", style("No request has been sent yet in this session.").dim()),
+        },
+        "/plan" => {
+            state.review_plans = !state.review_plans;
+            if state.review_plans {
+                println!("{}", style("✓ Plan review on: each proposed plan opens as JSON for you to edit before it runs.").green());
+            } else {
+                println!("{}", style("Plan review off: proposed plans run as before.").dim());
+            }
+        },
+        "/compare" => {
+            let refs: Vec<&str> = line.split_whitespace().skip(1).collect();
+            let [a, b] = refs[..] else {
+                println!("{}", style("Usage: /compare <branchA> <branchB>").yellow());
+                return Ok(());
+            };
+
+            let explanation = compare::explain(client, api_key, config, a, b, state.work_dir.as_deref()).await?;
+            println!("{}: {}", style("Jade").green().bold(), explanation);
+        },
+        "/commit" => {
+            let work_dir = state.work_dir.as_deref();
+            let message = commit::draft_message(client, api_key, config, work_dir).await?;
+            let command = shell::join(&["git", "commit", "-m", &message]);
+            let watch = ProgressWatch { client, api_key };
+            let head_before = git::head(work_dir);
+
+            match handle_execution(&command, false, config, work_dir, &watch, executor)? {
+                ExecutionOutcome::Ran { command, exit_code, .. } => {
+                    state.executed.push(command.clone());
+                    if exit_code == Some(0) {
+                        state.last_executed = Some(ExecutedCommand { command, head_before });
+                    }
+                },
+                ExecutionOutcome::Rejected { .. } => println!("{}", style("Commit cancelled, nothing was committed.").yellow()),
+                ExecutionOutcome::DryRun => {},
+            }
+        },
+        "/undo" => {
+            let Some(executed) = state.last_executed.take() else {
+                println!("{}", style("Nothing to undo: no command that changed the repository has run yet.").dim());
+                return Ok(());
+            };
+
+            let inverse = match undo::known_inverse(&executed) {
+                Some(inverse) => Some(inverse),
+                None => undo::ask_inverse(client, api_key, config, &executed).await?,
+            };
+            let Some(inverse) = inverse else {
+                println!("{}", style(format!("Jade does not know how to safely undo `{}`.", executed.command)).yellow());
+                return Ok(());
+            };
+
+            println!("Undoing: {}", style(&executed.command).cyan());
+            // Not plan-approved, so the inverse gets the same confirmations as any proposed command.
+            let watch = ProgressWatch { client, api_key };
+            match handle_execution(&inverse, false, config, state.work_dir.as_deref(), &watch, executor)? {
+                ExecutionOutcome::Ran { command, exit_code: Some(0), .. } => {
+                    state.executed.push(command);
+                    state.history.push(Message {
+                        role: "user".to_string(),
+                        content: format!("The user undid `{}` by running `{}`.", executed.command, inverse),
+                    });
+                },
+                ExecutionOutcome::Ran { command, .. } => {
+                    state.executed.push(command);
+                    state.last_executed = Some(executed);
+                },
+                ExecutionOutcome::Rejected { violation: Violation::UserRejected, .. } => {
+                    println!("{}", style("Nothing was undone.").dim());
+                    state.last_executed = Some(executed);
+                },
+                ExecutionOutcome::Rejected { reason, .. } => {
+                    println!("{}", style(format!("Undo refused: {}", reason)).yellow());
+                    state.last_executed = Some(executed);
+                },
+                ExecutionOutcome::DryRun => state.last_executed = Some(executed),
+            }
+        },
+        "/status" => print!("{}", get_git_status(true, state.work_dir.as_deref())),
+        "/use" => {
+            let target = line["/use".len()..].trim();
+            if target.is_empty() {
+                let current = state.work_dir.as_deref().map(|d| d.display().to_string());
+                println!("Active worktree: {}", style(current.unwrap_or_else(|| "current directory".to_string())).cyan());
+                return Ok(());
+            }
+
+            let dir = git::resolve_worktree(target, state.work_dir.as_deref())?;
+            println!("{}", style(format!("✓ Now operating on {}", dir.display())).green());
+            state.work_dir = Some(dir);
+        },
+        _ => println!("{}", style(format!("Unknown command: {}. Type /help to see all commands.", command)).yellow()),
+    }
+
+    Ok(())
+}
+
+pub async fn repl_step(
+    client: &Client,
+    api_key: &str,
+    state: &mut ReplState,
+    editor: &mut JadeEditor,
+    sessions: &SessionStore,
+    config: &mut Config,
+    executor: &dyn CommandExecutor,
+) -> Result<StepOutcome, Box<dyn std::error::Error>> {
+    if let Some(helper) = editor.helper_mut() {
+        helper.work_dir = state.work_dir.clone();
+    }
+    let input = read_user_input(editor, config)?;
+    // A Ctrl-C that cancelled the previous turn must not cancel whatever runs next, slash commands included.
+    cancel::reset();
+
+    match input {
+        UserInput::Prompt(prompt) => {
+            let result = tokio::select! {
+                result = run_turn(client, api_key, state, config, executor, Some(sessions), prompt) => result,
+                _ = cancel::cancelled() => Ok(()),
+            };
+
+            if cancel::requested() {
+                println!("{}", style("Turn cancelled.").yellow());
+                state.turn().outcome = TurnOutcome::Aborted;
+                return Ok(StepOutcome::Continue);
+            }
+            result.map(|_| StepOutcome::Continue)
+        },
+        UserInput::Command(command) => {
+            handle_slash_command(&command, state, sessions, client, api_key, config, executor).await.map(|_| StepOutcome::Continue)
+        },
+        UserInput::Quit => Ok(StepOutcome::Quit),
+    }
+}
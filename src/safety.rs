@@ -0,0 +1,573 @@
+use console::style;
+use dialoguer::Input;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::git;
+use crate::output::notice;
+
+const GENERIC_PHRASE: &str = "I understand";
+
+pub enum Escalation {
+    ForcePush,
+    HardReset,
+}
+
+impl Escalation {
+    pub fn detect(command: &str) -> Option<Escalation> {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        let is_git = |sub: &str| tokens.first() == Some(&"git") && tokens.contains(&sub);
+
+        if is_git("push") && tokens.iter().any(|t| is_force_flag(t)) {
+            return Some(Escalation::ForcePush);
+        }
+
+        if is_git("reset") && tokens.iter().any(|t| *t == "--hard" || t.starts_with("--hard=")) {
+            return Some(Escalation::HardReset);
+        }
+
+        None
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Escalation::ForcePush => "force-push rewrites history on the remote and can destroy other people's work",
+            Escalation::HardReset => "hard reset permanently discards uncommitted changes",
+        }
+    }
+
+    fn required_phrase(&self, work_dir: Option<&Path>) -> String {
+        match self {
+            Escalation::ForcePush => git::current_branch(work_dir).unwrap_or_else(|| GENERIC_PHRASE.to_string()),
+            Escalation::HardReset => GENERIC_PHRASE.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RiskKind {
+    ForcePush,
+    HardReset,
+    DeleteBranch,
+    DeleteTag,
+    CleanUntracked,
+    DiscardChanges,
+    DropStash,
+    Rebase,
+    Amend,
+    Push,
+}
+
+impl RiskKind {
+    fn detect(command: &str) -> Option<RiskKind> {
+        if let Some(escalation) = Escalation::detect(command) {
+            return Some(match escalation {
+                Escalation::ForcePush => RiskKind::ForcePush,
+                Escalation::HardReset => RiskKind::HardReset,
+            });
+        }
+
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        if tokens.first() != Some(&"git") {
+            return None;
+        }
+        let has = |t: &str| tokens.contains(&t);
+
+        let deletes_local_branch = has("branch") && (has("-d") || has("-D") || has("--delete"));
+        let deletes_remote_branch = has("push")
+            && (has("--delete") || has("-d") || tokens.iter().any(|t| t.starts_with(':') && t.len() > 1));
+
+        if deletes_local_branch || deletes_remote_branch {
+            Some(RiskKind::DeleteBranch)
+        } else if has("tag") && (has("-d") || has("--delete")) {
+            Some(RiskKind::DeleteTag)
+        } else if has("clean") && tokens.iter().any(|t| t.starts_with('-') && !t.starts_with("--") && t.contains('f')) {
+            Some(RiskKind::CleanUntracked)
+        } else if (has("checkout") && has("--")) || (has("restore") && !has("--staged")) {
+            Some(RiskKind::DiscardChanges)
+        } else if has("stash") && (has("drop") || has("clear")) {
+            Some(RiskKind::DropStash)
+        } else if has("rebase") {
+            Some(RiskKind::Rebase)
+        } else if has("commit") && has("--amend") {
+            Some(RiskKind::Amend)
+        } else if has("push") {
+            Some(RiskKind::Push)
+        } else {
+            None
+        }
+    }
+
+    fn describe(&self, count: usize) -> String {
+        let (singular, plural) = match self {
+            RiskKind::ForcePush => ("force-push", "force-push {} times"),
+            RiskKind::HardReset => ("hard-reset", "hard-reset {} times"),
+            RiskKind::DeleteBranch => ("delete a branch", "delete {} branches"),
+            RiskKind::DeleteTag => ("delete a tag", "delete {} tags"),
+            RiskKind::CleanUntracked => ("remove untracked files", "remove untracked files {} times"),
+            RiskKind::DiscardChanges => ("discard working tree changes", "discard working tree changes {} times"),
+            RiskKind::DropStash => ("drop stashed changes", "drop stashed changes {} times"),
+            RiskKind::Rebase => ("rebase", "rebase {} times"),
+            RiskKind::Amend => ("amend a commit", "amend {} commits"),
+            RiskKind::Push => ("push", "push {} times"),
+        };
+
+        if count == 1 { singular.to_string() } else { plural.replace("{}", &count.to_string()) }
+    }
+}
+
+pub fn plan_risks<S: AsRef<str>>(commands: &[S]) -> Vec<String> {
+    let mut counts: Vec<(RiskKind, usize)> = Vec::new();
+
+    for kind in commands.iter().filter_map(|c| RiskKind::detect(c.as_ref())) {
+        match counts.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((kind, 1)),
+        }
+    }
+
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    if total < 2 {
+        return Vec::new();
+    }
+
+    counts.iter().map(|(kind, count)| kind.describe(*count)).collect()
+}
+
+pub fn confirm_plan(config: &Config, risks: &[String], hooks: &[&str]) -> Result<bool, Box<dyn std::error::Error>> {
+    notice(config, style(format!("⚠ This plan will: {}", risks.join(", "))).red().bold());
+    print_hooks(config, hooks);
+
+    if !console::user_attended() {
+        notice(config, style("Cannot ask for confirmation without a terminal, refusing.").yellow());
+        return Ok(false);
+    }
+
+    let typed: String = Input::new()
+        .with_prompt(format!("Type '{}' to approve the whole plan", GENERIC_PHRASE))
+        .allow_empty(true)
+        .interact_text()?;
+
+    Ok(typed.trim() == GENERIC_PHRASE)
+}
+
+pub fn print_hooks(config: &Config, hooks: &[&str]) {
+    if !hooks.is_empty() {
+        notice(config, style(format!("Git hooks that will run: {}", hooks.join(", "))).cyan());
+    }
+}
+
+const WRAPPERS: &[&str] = &["sudo", "doas", "env", "command", "nohup", "xargs", "time", "nice"];
+
+pub fn is_destructive(command: &str) -> Option<&'static str> {
+    command.split(['&', '|', ';', '\n', '(', ')', '`']).find_map(|segment| {
+        let tokens: Vec<String> = segment
+            .split_whitespace()
+            .map(|t| t.trim_matches(|c| c == '\'' || c == '"').to_string())
+            .collect();
+
+        let start = tokens
+            .iter()
+            .position(|t| !t.contains('=') && !WRAPPERS.contains(&t.rsplit('/').next().unwrap_or(t)))?;
+        let program = tokens[start].rsplit('/').next().unwrap_or(&tokens[start]);
+        let args = &tokens[start + 1..];
+
+        match program {
+            "rm" => removes_recursively_and_forcefully(args)
+                .then_some("rm with recursive and force flags can delete whole directory trees"),
+            "git" => destructive_git(args),
+            _ => None,
+        }
+    })
+}
+
+fn removes_recursively_and_forcefully(args: &[String]) -> bool {
+    let short_flags: String = args
+        .iter()
+        .filter(|a| a.starts_with('-') && !a.starts_with("--"))
+        .flat_map(|a| a.chars().skip(1))
+        .collect();
+    let has_long = |flag: &str| args.iter().any(|a| a == flag);
+
+    let recursive = short_flags.contains(['r', 'R']) || has_long("--recursive");
+    let force = short_flags.contains('f') || has_long("--force");
+    recursive && force
+}
+
+fn destructive_git(args: &[String]) -> Option<&'static str> {
+    let has = |t: &str| args.iter().any(|a| a == t);
+    let short_cluster_has = |flag: char| args.iter().any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains(flag));
+
+    if has("reset") && args.iter().any(|a| a == "--hard" || a.starts_with("--hard=")) {
+        Some("git reset --hard permanently discards uncommitted changes")
+    } else if has("clean") && (short_cluster_has('f') || has("--force")) {
+        Some("git clean with --force permanently deletes untracked files")
+    } else if has("push") && args.iter().any(|a| is_force_flag(a)) {
+        Some("force-pushing rewrites history on the remote")
+    } else {
+        None
+    }
+}
+
+// Commands are run without a terminal, so anything that waits for an editor or per-hunk answers would fail or hang.
+pub fn needs_interactive_input(command: &str) -> Option<&'static str> {
+    let segments = segments(command).unwrap_or_else(|| vec![command.to_string()]);
+    segments.iter().find_map(|segment| {
+        let tokens: Vec<&str> = without_assignments(segment).split_whitespace().collect();
+        match tokens.as_slice() {
+            [git, subcommand, args @ ..] if git.rsplit('/').next() == Some("git") => interactive_git(subcommand, args),
+            _ => None,
+        }
+    })
+}
+
+fn interactive_git(subcommand: &str, args: &[&str]) -> Option<&'static str> {
+    let has = |flags: &[&str]| args.iter().any(|a| flags.iter().any(|f| a == f || a.starts_with(&format!("{}=", f))));
+    let short_cluster_has = |flag: char| args.iter().any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains(flag));
+
+    match subcommand {
+        "rebase" if short_cluster_has('i') || has(&["--interactive"]) => {
+            Some("`git rebase -i` opens an editor for the todo list. Rebase onto an explicit ref without -i (e.g. `git rebase main`), or squash with `git reset --soft <ref>` and a new commit")
+        },
+        "add" | "checkout" | "reset" | "restore" | "stash" if short_cluster_has('p') || has(&["--patch"]) => {
+            Some("--patch asks about every hunk interactively. Name the files or paths to act on instead")
+        },
+        "add" | "clean" if short_cluster_has('i') || has(&["--interactive"]) => {
+            Some("--interactive waits for menu choices. Name the files or paths to act on instead")
+        },
+        "commit" if !short_cluster_has('m') && !short_cluster_has('F') && !short_cluster_has('C')
+            && !has(&["--message", "--file", "--reuse-message", "--no-edit", "--amend", "--fixup"]) => {
+            Some("`git commit` without a message opens an editor. Pass the message with -m \"...\"")
+        },
+        _ => None,
+    }
+}
+
+pub fn needs_privilege(command: &str) -> bool {
+    command
+        .split(['&', '|', ';', '(', ')', '`', '\n'])
+        .filter_map(|part| part.split_whitespace().find(|t| !t.contains('=') || t.starts_with('-')))
+        .any(|program| matches!(program.rsplit('/').next(), Some("sudo" | "doas" | "su" | "pkexec")))
+}
+
+// Splits a command line into pieces at unquoted `;`, `&&`, `||`, `|`, `&`, parentheses and newlines, pairing each
+// piece with the operator that ends it (None for the last one). None when it uses command substitution, since the
+// substituted command could run anything.
+fn split_operators(command: &str) -> Option<Vec<(String, Option<&'static str>)>> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {},
+            (_, '`') => return None,
+            (_, '$') if chars.peek() == Some(&'(') => return None,
+            (Some(q), c) if c == q => quote = None,
+            (_, '\\') => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                continue;
+            },
+            (Some(_), _) => {},
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '&') if current.ends_with(['>', '<']) => {},
+            (None, ';' | '&' | '|' | '(' | ')' | '\n') => {
+                let operator = match c {
+                    '&' if chars.next_if_eq(&'&').is_some() => "&&",
+                    '|' if chars.next_if_eq(&'|').is_some() => "||",
+                    '&' => "&",
+                    '|' => "|",
+                    ';' => ";",
+                    '(' => "(",
+                    ')' => ")",
+                    _ => "\n",
+                };
+                pieces.push((std::mem::take(&mut current), Some(operator)));
+                continue;
+            },
+            (None, _) => {},
+        }
+        current.push(c);
+    }
+
+    pieces.push((current, None));
+    Some(pieces)
+}
+
+// The commands in a command line, split as `split_operators` does.
+pub fn segments(command: &str) -> Option<Vec<String>> {
+    let pieces = split_operators(command)?;
+    Some(pieces.into_iter().map(|(s, _)| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+}
+
+const SHELL_STATE_BUILTINS: &[&str] = &["cd", "pushd", "popd", "export", "unset", "source", ".", "set", "alias", "umask", "eval"];
+
+// Splits `a && b && c` into its steps so each can run (and fail) on its own. None for anything else, including
+// chains that also use pipes, `;`, `||`, subshells or substitution, and chains where a step changes the shell's
+// state (`cd sub && git add .`), since every step runs in a shell of its own.
+pub fn and_chain(command: &str) -> Option<Vec<String>> {
+    let pieces = split_operators(command)?;
+    let (_, chained) = pieces.split_last()?;
+    if chained.iter().any(|(_, operator)| *operator != Some("&&")) {
+        return None;
+    }
+
+    let steps: Vec<String> = pieces.iter().map(|(s, _)| s.trim().to_string()).collect();
+    (steps.len() > 1 && steps.iter().all(|s| !s.is_empty() && !changes_shell_state(s))).then_some(steps)
+}
+
+fn changes_shell_state(step: &str) -> bool {
+    let rest = without_assignments(step);
+    let program = rest.split_whitespace().next().unwrap_or("");
+    // A step made only of `NAME=value` words sets shell variables for the steps after it.
+    let only_assignments = !rest.contains(char::is_whitespace) && rest.contains('=') && !rest.starts_with('-');
+    only_assignments || SHELL_STATE_BUILTINS.contains(&program)
+}
+
+// The segment without leading `NAME=value` environment assignments.
+fn without_assignments(segment: &str) -> &str {
+    let mut rest = segment;
+    while let Some((word, tail)) = rest.split_once(char::is_whitespace) {
+        if !word.contains('=') || word.starts_with('-') {
+            break;
+        }
+        rest = tail.trim_start();
+    }
+    rest
+}
+
+pub fn runs_only_git(command: &str) -> bool {
+    let Some(segments) = segments(command) else { return false };
+    !segments.is_empty()
+        && segments.iter().all(|segment| {
+            let program = without_assignments(segment).split_whitespace().next().unwrap_or("");
+            program.rsplit('/').next() == Some("git")
+        })
+}
+
+#[derive(Default)]
+pub struct CommandPolicy {
+    pub allowlist: Vec<String>,
+    pub blocklist: Vec<String>,
+}
+
+impl CommandPolicy {
+    // Patterns are prefixes of whole words, checked against every command in the line.
+    pub fn check(&self, command: &str) -> Result<(), String> {
+        let matches = |segment: &str, prefix: &str| {
+            let prefix = prefix.trim();
+            segment.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        };
+
+        let Some(segments) = segments(command) else {
+            if self.allowlist.is_empty() {
+                return Ok(());
+            }
+            return Err("Command substitution is not allowed by the command allowlist.".to_string());
+        };
+
+        for segment in segments.iter().map(|s| without_assignments(s)) {
+            if let Some(pattern) = self.blocklist.iter().find(|p| matches(segment, p)) {
+                return Err(format!("`{}` is blocked by the command blocklist (`{}`).", segment, pattern.trim()));
+            }
+            if !self.allowlist.is_empty() && !self.allowlist.iter().any(|p| matches(segment, p)) {
+                return Err(format!(
+                    "`{}` is not on the command allowlist. Allowed commands start with: {}.",
+                    segment,
+                    self.allowlist.iter().map(|p| p.trim()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn confirm_escalated(
+    config: &Config,
+    command: &str,
+    escalation: &Escalation,
+    work_dir: Option<&Path>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let phrase = escalation.required_phrase(work_dir);
+
+    notice(config, style(format!("⚠ Dangerous command: {}", command.trim())).red().bold());
+    notice(config, style(format!("This {}.", escalation.description())).yellow());
+    print_hooks(config, &git::hooks_for(command, work_dir));
+
+    if !console::user_attended() {
+        notice(config, style("Cannot ask for confirmation without a terminal, refusing.").yellow());
+        return Ok(false);
+    }
+
+    let typed: String = Input::new()
+        .with_prompt(format!("Type '{}' to proceed", phrase))
+        .allow_empty(true)
+        .interact_text()?;
+
+    Ok(typed.trim() == phrase)
+}
+
+fn is_force_flag(token: &str) -> bool {
+    token == "-f"
+        || token == "--force"
+        || token == "--force-with-lease"
+        || token.starts_with("--force-with-lease=")
+        || (token.starts_with('+') && token.len() > 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destructive_commands_are_caught_despite_spacing_and_flag_order() {
+        for command in [
+            "rm -rf build",
+            "rm  -rf build",
+            "rm -fr build",
+            "rm -r -f build",
+            "rm --recursive --force build",
+            "/bin/rm -Rf build",
+            "git status && rm -rf .",
+            "sudo rm -rf /tmp/x",
+            "git clean -fd",
+            "git clean -xdf",
+            "git clean --force -d",
+            "git reset --hard",
+            "git reset  --hard HEAD~1",
+            "git reset --hard=HEAD",
+            "git push --force",
+            "git push -f origin main",
+            "git push origin +main",
+            "git push --force-with-lease=main",
+        ] {
+            assert!(is_destructive(command).is_some(), "{} should be destructive", command);
+        }
+    }
+
+    #[test]
+    fn safe_commands_are_not_destructive() {
+        for command in [
+            "rm file.txt",
+            "rm -r empty_dir",
+            "git clean -n",
+            "git clean -dn",
+            "git reset --soft HEAD~1",
+            "git reset HEAD file",
+            "git push origin main",
+            "git commit -m 'rm -rf is scary'",
+            "grep -rf patterns.txt .",
+        ] {
+            assert_eq!(is_destructive(command), None, "{} should not be destructive", command);
+        }
+    }
+
+    #[test]
+    fn git_only_mode_accepts_git_command_lines() {
+        for command in [
+            "git status",
+            "/usr/bin/git log --oneline -n 5 2>&1",
+            "GIT_PAGER=cat git log HEAD~1",
+            "git add -A && git commit -m 'Fix redirect (closes #3); tidy | trim'",
+            "git commit -m \"say \\\"hi\\\" & leave\"",
+        ] {
+            assert!(runs_only_git(command), "{} was rejected", command);
+        }
+    }
+
+    #[test]
+    fn git_only_mode_rejects_other_programs() {
+        for command in [
+            "",
+            "ls -la",
+            "git status; rm -rf build",
+            "git log | head -n 5",
+            "git commit -m \"$(whoami)\"",
+            "git commit -m `id`",
+            "(cd .. && make)",
+            "gitk --all",
+        ] {
+            assert!(!runs_only_git(command), "{} was accepted", command);
+        }
+    }
+
+    #[test]
+    fn segments_split_on_unquoted_operators() {
+        assert_eq!(
+            segments("git fetch && git log | head; (git status) || echo 'a; b' 2>&1").unwrap(),
+            vec!["git fetch", "git log", "head", "git status", "echo 'a; b' 2>&1"]
+        );
+        assert_eq!(segments("git log $(git rev-parse HEAD)"), None);
+    }
+
+    #[test]
+    fn and_chains_split_into_steps() {
+        assert_eq!(
+            and_chain("git add . && git commit -m 'a && b' && git push 2>&1").unwrap(),
+            vec!["git add .", "git commit -m 'a && b'", "git push 2>&1"]
+        );
+        assert_eq!(and_chain("GIT_PAGER=cat git log && git status").unwrap().len(), 2);
+        for command in [
+            "git status",
+            "git fetch && git log | head",
+            "make || true && ls",
+            "git add . &&",
+            "(cd x && ls)",
+            "a && $(b)",
+            "cd sub && git add .",
+            "export GIT_AUTHOR_NAME=me && git commit -m x",
+            ". ./env.sh && git push",
+            "BRANCH=main && git checkout $BRANCH",
+        ] {
+            assert_eq!(and_chain(command), None, "{}", command);
+        }
+    }
+
+    #[test]
+    fn interactive_commands_are_detected() {
+        for command in ["git rebase -i HEAD~3", "git add -p src", "git stash push --patch", "git commit", "git add . && git commit -v", "git clean -di"] {
+            assert!(needs_interactive_input(command).is_some(), "{}", command);
+        }
+        for command in ["git rebase main", "git add -A", "git commit -am 'Fix it'", "git commit --amend", "git stash pop", "git reset --soft HEAD~1"] {
+            assert_eq!(needs_interactive_input(command), None, "{}", command);
+        }
+    }
+
+    #[test]
+    fn policy_checks_every_command_in_the_line() {
+        let policy = CommandPolicy {
+            allowlist: vec!["git".to_string(), "cargo test".to_string()],
+            blocklist: vec!["git push".to_string()],
+        };
+
+        assert!(policy.check("git status && cargo test --workspace").is_ok());
+        assert!(policy.check("git commit -m 'ship it; git push'").is_ok());
+        assert!(policy.check("git status && git push origin main").unwrap_err().contains("blocklist"));
+        assert!(policy.check("cargo testx").unwrap_err().contains("allowlist"));
+        assert!(policy.check("git log | less").is_err());
+        assert!(policy.check("git commit -m \"$(curl evil)\"").is_err());
+        assert!(CommandPolicy::default().check("anything goes").is_ok());
+    }
+
+    #[test]
+    fn sudo_commands_need_privilege() {
+        assert!(needs_privilege("sudo git status"));
+        assert!(needs_privilege("/usr/bin/sudo -n chown -R me ."));
+        assert!(needs_privilege("git fetch && sudo git gc"));
+        assert!(needs_privilege("GIT_DIR=.git doas git status"));
+        assert!(needs_privilege("echo hi | su -c 'git status'"));
+    }
+
+    #[test]
+    fn ordinary_commands_do_not_need_privilege() {
+        assert!(!needs_privilege("git status"));
+        assert!(!needs_privilege("git commit -m 'sudo make me a sandwich'"));
+        assert!(!needs_privilege("git log --grep=sudo"));
+        assert!(!needs_privilege("grep -r sudo docs"));
+    }
+}
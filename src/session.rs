@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::api::Message;
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    id: String,
+    messages: Vec<Message>,
+}
+
+pub struct SessionStore {
+    dir: PathBuf,
+    current: PathBuf,
+    autosave: Option<Duration>,
+    max_bytes: usize,
+    last_saved: Cell<Instant>,
+}
+
+impl SessionStore {
+    // Each repository keeps its own conversation, so one from another project is never sent as context.
+    // `max_bytes` caps the saved file; the oldest messages are left out of it once it grows past that (0 means no cap).
+    pub fn new(jade_dir: PathBuf, repo_root: &Path, autosave: Option<Duration>, max_bytes: usize) -> SessionStore {
+        let key: String = repo_root.to_string_lossy().chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).collect();
+        SessionStore {
+            current: jade_dir.join("current").join(format!("{}.json", key)),
+            dir: jade_dir,
+            autosave,
+            max_bytes,
+            last_saved: Cell::new(Instant::now()),
+        }
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.current.clone()
+    }
+
+    fn archive_dir(&self) -> PathBuf {
+        self.dir.join("sessions")
+    }
+
+    pub fn load_current(&self) -> Vec<Message> {
+        fs::read_to_string(self.current_path())
+            .ok()
+            .and_then(|raw| serde_json::from_str::<SessionFile>(&raw).ok())
+            .map(|session| session.messages)
+            .unwrap_or_default()
+    }
+
+    pub fn save_current(&self, history: &[Message]) -> Result<(), Box<dyn std::error::Error>> {
+        self.last_saved.set(Instant::now());
+        write_session(&self.current_path(), newest_within(history, self.max_bytes))
+    }
+
+    pub fn delete_current(&self) -> io::Result<()> {
+        match fs::remove_file(self.current_path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    // The REPL saves after every step; this also saves during a long turn, at most once per autosave interval.
+    pub fn checkpoint(&self, history: &[Message]) {
+        if self.autosave.is_some_and(|interval| self.last_saved.get().elapsed() >= interval) {
+            let _ = self.save_current(history);
+        }
+    }
+
+    pub fn archive(&self, history: &[Message]) -> Result<String, Box<dyn std::error::Error>> {
+        fs::create_dir_all(self.archive_dir())?;
+
+        let id = new_session_id();
+        let session = SessionFile {
+            id: id.clone(),
+            messages: history.to_vec(),
+        };
+        fs::write(self.archive_dir().join(format!("{}.json", id)), serde_json::to_string_pretty(&session)?)?;
+
+        self.save_current(&[])?;
+        Ok(id)
+    }
+}
+
+fn write_session(path: &Path, history: &[Message]) -> Result<(), Box<dyn std::error::Error>> {
+    let session = SessionFile {
+        id: "current".to_string(),
+        messages: history.to_vec(),
+    };
+    let contents = serde_json::to_string_pretty(&session)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn newest_within(history: &[Message], max_bytes: usize) -> &[Message] {
+    if max_bytes == 0 {
+        return history;
+    }
+
+    let sizes: Vec<usize> = history.iter().map(|m| serde_json::to_string_pretty(m).map_or(0, |s| s.len())).collect();
+    let mut total: usize = sizes.iter().sum();
+    let mut start = 0;
+    while total > max_bytes && start + 1 < history.len() {
+        total -= sizes[start];
+        start += 1;
+    }
+    &history[start..]
+}
+
+fn new_session_id() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}-{:03}", now.as_secs(), now.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_messages_are_pruned_past_the_cap() {
+        let history: Vec<Message> = (0..10)
+            .map(|i| Message { role: "user".to_string(), content: format!("message {} {}", i, "x".repeat(100)) })
+            .collect();
+
+        assert_eq!(newest_within(&history, 0).len(), 10);
+        let kept = newest_within(&history, 500);
+        assert!(kept.len() < 10 && !kept.is_empty());
+        assert!(kept.last().unwrap().content.starts_with("message 9"));
+        assert_eq!(newest_within(&history, 1).len(), 1);
+    }
+}
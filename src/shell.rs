@@ -0,0 +1,228 @@
+use std::process::Command;
+
+const SHELL_METACHARACTERS: &[char] = &[';', '&', '|', '<', '>', '(', ')', '`', '$', '*', '?', '[', '#', '~', '{', '}'];
+
+pub fn split(command: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            },
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            },
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => current.push(c),
+                            '\n' => {},
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            },
+                        },
+                        '$' | '`' => return None,
+                        c => current.push(c),
+                    }
+                }
+            },
+            '\\' => {
+                in_arg = true;
+                match chars.next()? {
+                    '\n' => {},
+                    c => current.push(c),
+                }
+            },
+            c if SHELL_METACHARACTERS.contains(&c) => return None,
+            c => {
+                in_arg = true;
+                current.push(c);
+            },
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    Some(args)
+}
+
+pub fn quote(arg: &str) -> String {
+    if arg.is_empty() {
+        return "''".to_string();
+    }
+
+    let is_safe = arg
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | '@' | '+' | ',' | '%' | '^'));
+
+    if is_safe {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+pub fn join<S: AsRef<str>>(args: &[S]) -> String {
+    args.iter()
+        .map(|arg| quote(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn command(command: &str) -> Command {
+    command_in(command, false)
+}
+
+pub fn command_in(command: &str, login_shell: bool) -> Command {
+    let mut cmd = if login_shell && !cfg!(target_os = "windows") {
+        // The profile a login shell sources may export its own pager, so override it again afterwards.
+        let mut cmd = Command::new("bash");
+        cmd.arg("-lic").arg(format!("export GIT_PAGER=cat PAGER=cat GIT_EDITOR=true GIT_SEQUENCE_EDITOR=true; {}", command));
+        cmd
+    } else if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    // There is no terminal to edit in, so editors accept what git prepared (e.g. the default merge message).
+    cmd.env("GIT_PAGER", "cat").env("PAGER", "cat").env("GIT_EDITOR", "true").env("GIT_SEQUENCE_EDITOR", "true");
+    cmd
+}
+
+pub fn strip_job_control_warnings(stderr: &str) -> String {
+    stderr
+        .lines()
+        .filter(|line| !line.starts_with("bash: cannot set terminal process group") && *line != "bash: no job control in this shell")
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRICKY: &[&str] = &[
+        "plain",
+        "",
+        "with spaces",
+        "it's",
+        "say \"hi\"",
+        "`whoami`",
+        "$HOME and ${PATH}",
+        "$(rm -rf /)",
+        "first line\nsecond line",
+        "back\\slash",
+        "mixed 'single' \"double\" `tick` $var\n\tend",
+    ];
+
+    #[test]
+    fn quote_leaves_safe_words_alone() {
+        assert_eq!(quote("git"), "git");
+        assert_eq!(quote("origin/main"), "origin/main");
+        assert_eq!(quote("--message=x"), "--message=x");
+    }
+
+    #[test]
+    fn quote_wraps_empty_and_unsafe_args() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn split_handles_quotes_and_escapes() {
+        assert_eq!(
+            split(r#"git commit -m "fix: \"quoted\" bug" --author='A B'"#),
+            Some(vec![
+                "git".to_string(),
+                "commit".to_string(),
+                "-m".to_string(),
+                "fix: \"quoted\" bug".to_string(),
+                "--author=A B".to_string(),
+            ])
+        );
+        assert_eq!(split(r"a\ b ''"), Some(vec!["a b".to_string(), "".to_string()]));
+    }
+
+    #[test]
+    fn split_rejects_unbalanced_quotes_and_shell_syntax() {
+        assert_eq!(split("git commit -m \"unterminated"), None);
+        assert_eq!(split("git add . && git commit"), None);
+        assert_eq!(split("echo $HOME"), None);
+        assert_eq!(split("echo \"$HOME\""), None);
+        assert_eq!(split("echo `id`"), None);
+    }
+
+    #[test]
+    fn split_round_trips_joined_args() {
+        let args: Vec<String> = TRICKY.iter().map(|s| s.to_string()).collect();
+        assert_eq!(split(&join(&args)), Some(args));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn git_commands_never_wait_on_a_pager() {
+        let repo = std::env::temp_dir().join(format!("jade-pager-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(&repo).unwrap();
+
+        let git = |script: &str| {
+            let status = Command::new("sh").arg("-c").arg(script).current_dir(&repo).status().unwrap();
+            assert!(status.success(), "{} failed", script);
+        };
+        git("git init -q && git config user.email jade@example.com && git config user.name Jade");
+        git("git config core.pager 'sleep 60'");
+        for i in 0..3 {
+            git(&format!("git commit -q --allow-empty -m 'commit {}'", i));
+        }
+
+        let started = std::time::Instant::now();
+        let output = command("git --paginate log --oneline").current_dir(&repo).output().unwrap();
+        let _ = std::fs::remove_dir_all(&repo);
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(30));
+        assert!(output.status.success());
+        let log = String::from_utf8_lossy(&output.stdout);
+        for i in 0..3 {
+            assert!(log.contains(&format!("commit {}", i)), "missing commit {} in {:?}", i, log);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quoted_args_reach_the_shell_verbatim() {
+        for arg in TRICKY {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", quote(arg)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), *arg);
+        }
+    }
+}
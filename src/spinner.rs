@@ -0,0 +1,66 @@
+use console::{style, Term};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::progress;
+
+const FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const TICK: Duration = Duration::from_millis(100);
+
+pub struct Spinner {
+    // Held while drawing so a frame can never land after `stop` cleared the line.
+    active: Arc<Mutex<bool>>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    // Falls back to a plain progress line when stderr is not a terminal.
+    pub fn start(config: &Config, message: &str) -> Spinner {
+        let active = Arc::new(Mutex::new(false));
+        if config.quiet || !console::user_attended_stderr() {
+            progress(config, format!("{}...", message));
+            return Spinner { active, ticker: None };
+        }
+
+        *active.lock().unwrap() = true;
+        let drawing = active.clone();
+        let message = message.to_string();
+        let started = Instant::now();
+        let ticker = tokio::spawn(async move {
+            for frame in FRAMES.iter().cycle() {
+                {
+                    let active = drawing.lock().unwrap();
+                    if !*active {
+                        break;
+                    }
+                    let term = Term::stderr();
+                    let _ = term.clear_line();
+                    let _ = term.write_str(&format!(
+                        "{} {}",
+                        style(frame).cyan(),
+                        style(format!("{} ({}s)", message, started.elapsed().as_secs())).dim()
+                    ));
+                }
+                tokio::time::sleep(TICK).await;
+            }
+        });
+
+        Spinner { active, ticker: Some(ticker) }
+    }
+
+    pub fn stop(&mut self) {
+        let Some(ticker) = self.ticker.take() else { return };
+        let mut active = self.active.lock().unwrap();
+        *active = false;
+        let _ = Term::stderr().clear_line();
+        ticker.abort();
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
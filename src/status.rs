@@ -0,0 +1,165 @@
+use console::{style, Color};
+
+type StatusGroup<'a> = (&'static str, Vec<(&'a str, &'a str)>, Color);
+
+#[derive(Default)]
+pub struct GitStatus {
+    branch: Option<String>,
+    upstream: Option<String>,
+    // Missing when the upstream branch was deleted on the remote.
+    ahead_behind: Option<(u32, u32)>,
+    staged: Vec<(&'static str, String)>,
+    unstaged: Vec<(&'static str, String)>,
+    conflicted: Vec<String>,
+    untracked: Vec<String>,
+}
+
+impl GitStatus {
+    pub fn parse(porcelain: &str) -> GitStatus {
+        let mut status = GitStatus::default();
+
+        for line in porcelain.lines() {
+            if let Some(header) = line.strip_prefix("# ") {
+                status.parse_header(header);
+                continue;
+            }
+
+            let mut fields = line.splitn(2, ' ');
+            let kind = fields.next().unwrap_or("");
+            let rest = fields.next().unwrap_or("");
+
+            match kind {
+                "1" | "2" => {
+                    let field_count = if kind == "1" { 8 } else { 9 };
+                    let parts: Vec<&str> = rest.splitn(field_count, ' ').collect();
+                    let (Some(xy), Some(path)) = (parts.first(), parts.last()) else { continue };
+                    let path = match path.split_once('\t') {
+                        Some((new, old)) => format!("{} -> {}", old, new),
+                        None => path.to_string(),
+                    };
+
+                    let mut codes = xy.chars();
+                    if let Some(label) = codes.next().and_then(change_label) {
+                        status.staged.push((label, path.clone()));
+                    }
+                    if let Some(label) = codes.next().and_then(change_label) {
+                        status.unstaged.push((label, path));
+                    }
+                },
+                "u" => {
+                    if let Some(path) = rest.splitn(10, ' ').last() {
+                        status.conflicted.push(path.to_string());
+                    }
+                },
+                "?" => status.untracked.push(rest.to_string()),
+                _ => {},
+            }
+        }
+
+        status
+    }
+
+    fn parse_header(&mut self, header: &str) {
+        if let Some(head) = header.strip_prefix("branch.head ") {
+            self.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(upstream) = header.strip_prefix("branch.upstream ") {
+            self.upstream = Some(upstream.to_string());
+        } else if let Some(counts) = header.strip_prefix("branch.ab ") {
+            let (mut ahead, mut behind) = (0, 0);
+            for count in counts.split_whitespace() {
+                if let Some(n) = count.strip_prefix('+') {
+                    ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = count.strip_prefix('-') {
+                    behind = n.parse().unwrap_or(0);
+                }
+            }
+            self.ahead_behind = Some((ahead, behind));
+        }
+    }
+
+    fn branch_line(&self) -> String {
+        let mut line = match &self.branch {
+            Some(branch) => format!("On branch {}", branch),
+            None => "HEAD detached".to_string(),
+        };
+
+        match (&self.upstream, self.ahead_behind) {
+            (Some(upstream), Some((ahead, behind))) => {
+                line.push_str(&format!(" (tracking {}, ahead {}, behind {})", upstream, ahead, behind));
+            },
+            (Some(upstream), None) => line.push_str(&format!(" (tracking {}, which no longer exists on the remote)", upstream)),
+            (None, _) if self.branch.is_some() => line.push_str(" (no upstream tracking branch)"),
+            (None, _) => {},
+        }
+        line
+    }
+
+    pub fn render(&self, colored: bool) -> String {
+        let paint = |text: &str, color: Color| {
+            if colored { style(text).fg(color).to_string() } else { text.to_string() }
+        };
+
+        let mut out = if colored { style(self.branch_line()).bold().to_string() } else { self.branch_line() };
+        out.push('\n');
+
+        let groups: [StatusGroup; 4] = [
+            ("Staged", self.staged.iter().map(|(l, p)| (*l, p.as_str())).collect(), Color::Green),
+            ("Unstaged", self.unstaged.iter().map(|(l, p)| (*l, p.as_str())).collect(), Color::Red),
+            ("Conflicted", self.conflicted.iter().map(|p| ("conflict", p.as_str())).collect(), Color::Magenta),
+            ("Untracked", self.untracked.iter().map(|p| ("new", p.as_str())).collect(), Color::Yellow),
+        ];
+
+        let width = groups
+            .iter()
+            .flat_map(|(_, entries, _)| entries.iter().map(|(label, _)| label.len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut clean = true;
+        for (title, entries, color) in groups {
+            if entries.is_empty() {
+                continue;
+            }
+            clean = false;
+
+            out.push_str(&format!("{} ({}):\n", title, entries.len()));
+            for (label, path) in entries {
+                out.push_str(&format!("  {:<width$}  {}\n", label, paint(path, color), width = width));
+            }
+        }
+
+        if clean {
+            out.push_str("Working tree clean\n");
+        }
+        out
+    }
+}
+
+fn change_label(code: char) -> Option<&'static str> {
+    match code {
+        'M' => Some("modified"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        'T' => Some("typechange"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_line_describes_tracking() {
+        let tracking = "# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n";
+        assert_eq!(GitStatus::parse(tracking).branch_line(), "On branch main (tracking origin/main, ahead 2, behind 1)");
+
+        let gone = "# branch.head topic\n# branch.upstream origin/topic\n";
+        assert_eq!(GitStatus::parse(gone).branch_line(), "On branch topic (tracking origin/topic, which no longer exists on the remote)");
+
+        assert_eq!(GitStatus::parse("# branch.head topic\n").branch_line(), "On branch topic (no upstream tracking branch)");
+        assert_eq!(GitStatus::parse("# branch.head (detached)\n").branch_line(), "HEAD detached");
+    }
+}
@@ -0,0 +1,876 @@
+use console::style;
+use dialoguer::Confirm;
+use reqwest::Client;
+use std::io::{self, Write};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::runtime::RuntimeFlavor;
+
+use crate::api::{send_chat, stream_chat, Message, Usage};
+use crate::commit::{CommitCommand, CommitReview};
+use crate::config::Config;
+use crate::corrections::{Corrections, Violation};
+use crate::plan::Plan;
+use crate::protocol::{self, Line, ParsedResponse};
+use crate::repl::{ReplState, TurnOutcome, TurnStatus};
+use crate::safety::{self, Escalation};
+use crate::session::SessionStore;
+use crate::spinner::Spinner;
+use crate::undo::ExecutedCommand;
+use crate::status::GitStatus;
+use crate::exec::{self, CommandExecutor};
+use crate::output::{emit, notice, Event};
+use crate::{audit, cancel, context, diagnostics, explain, undo, git, patch, postprocess, progress, shell, truncate_diff, truncate_output};
+
+const SYSTEM_PROMPT: &str = include_str!("prompts/system_prompt.txt");
+const RATIONALE_PROMPT: &str = include_str!("prompts/rationale.txt");
+
+const MAX_DIFF_CHARS: usize = 8000;
+
+pub fn add_llm_correction(config: &Config, command: &str, correction_message: &str, history: &mut Vec<Message>) {
+    if !config.quiet {
+        println!("{}", style(format!("LLM correction message: {}", correction_message)).yellow().dim());
+    }
+
+    history.push(Message {
+        role: "user".to_string(),
+        content: format!("ERROR: {} command is invalid. {}\nEnsure future queries don't make this mistake again.", command, correction_message),
+    });
+}
+
+pub fn get_git_status(colored: bool, work_dir: Option<&Path>) -> String {
+    let output = git::command(work_dir).args(["status", "--porcelain=v2", "--branch"]).output();
+    match output {
+        Ok(o) if o.status.success() => GitStatus::parse(&String::from_utf8_lossy(&o.stdout)).render(colored),
+        Ok(o) => {
+            let error_msg = String::from_utf8_lossy(&o.stderr).trim().to_string();
+            if error_msg.is_empty() { "Git command failed, no error message.".to_string() } else { error_msg }
+        },
+        Err(e) => format!("Critical Error: Could not execute 'git'. Details: {}", e),
+    }
+}
+
+pub fn get_git_diff(exclude: &[String], context_lines: Option<u32>, work_dir: Option<&Path>) -> String {
+    let mut stats = Vec::new();
+    let mut sections = Vec::new();
+
+    for (label, staged) in [("Unstaged", false), ("Staged", true)] {
+        let run_diff = |stat: bool| {
+            let mut cmd = git::command(work_dir);
+            cmd.args(["--no-pager", "diff"]);
+            if staged {
+                cmd.arg("--cached");
+            }
+            if stat {
+                cmd.arg("--stat");
+            } else if let Some(lines) = context_lines {
+                cmd.arg(format!("-U{}", lines));
+            }
+            if !exclude.is_empty() {
+                cmd.arg("--");
+                cmd.args(exclude.iter().map(|glob| format!(":(exclude){}", glob)));
+            }
+
+            match cmd.output() {
+                Ok(o) if o.status.success() => Some(String::from_utf8_lossy(&o.stdout).trim_end().to_string()),
+                _ => None,
+            }
+        };
+
+        let Some(diff) = run_diff(false).filter(|d| !d.trim().is_empty()) else { continue };
+        if let Some(stat) = run_diff(true) {
+            stats.push(format!("{} files:\n{}", label, stat));
+        }
+        sections.push(format!("{} changes:\n{}", label, diff));
+    }
+
+    if sections.is_empty() {
+        return "No changes.".to_string();
+    }
+
+    format!("{}\n\n{}", stats.join("\n\n"), truncate_diff(sections.join("\n\n"), MAX_DIFF_CHARS))
+}
+
+// Conventions from `prompt.txt` in the Jade directory and `.jade/prompt.txt` in the repository, appended to the built-in prompt.
+fn custom_instructions(config: &Config, work_dir: Option<&Path>) -> Option<String> {
+    let global = config.config_file.parent().map(|dir| dir.join("prompt.txt"));
+    let repo = git::toplevel(work_dir.unwrap_or(Path::new("."))).map(|root| root.join(".jade").join("prompt.txt"));
+
+    let instructions: Vec<String> = [global, repo]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect();
+    (!instructions.is_empty()).then(|| format!("## Project Instructions\n{}", instructions.join("\n\n")))
+}
+
+pub async fn get_llm_response(
+    client: &Client,
+    api_key: &str,
+    user_input: &str,
+    git_context: &str,
+    state: &mut ReplState,
+    config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut sections = vec![SYSTEM_PROMPT.to_string()];
+    if config.require_rationale {
+        sections.push(RATIONALE_PROMPT.to_string());
+    }
+    sections.extend(custom_instructions(config, state.work_dir.as_deref()));
+    sections.push(git_context.to_string());
+    let system_msg = Message {
+        role: "system".to_string(),
+        content: sections.join("\n\n"),
+    };
+
+    progress(config, "Processing...");
+
+    if !user_input.trim().is_empty() {
+        state.history.push(Message {
+            role: "user".to_string(),
+            content: user_input.to_string(),
+        });
+    }
+
+    if config.summarize_after > 0 && state.history.len() > config.summarize_after {
+        progress(config, "Summarizing earlier history...");
+        if let Err(e) = context::summarize_oldest(client, api_key, config, &mut state.history).await {
+            notice(config, style(format!("Could not summarize history ({}), dropping old messages instead.", e)).yellow());
+        }
+    }
+
+    let dropped = context::trim_to_budget(&mut state.history, &system_msg, config.context_budget_tokens);
+    if dropped > 0 {
+        progress(config, format!("Dropped {} old messages to stay within the context budget.", dropped));
+    }
+
+    let mut request_messages = vec![system_msg];
+    request_messages.extend(state.history.clone());
+
+    let mut spinner = Spinner::start(config, "Thinking");
+    let (content, raw_body, usage) = stream_chat(client, api_key, request_messages, config, |token| {
+        spinner.stop();
+        if !config.quiet {
+            print!("{}", style(token).dim());
+            let _ = io::stdout().flush();
+        }
+    }).await?;
+    spinner.stop();
+    if !config.quiet {
+        println!();
+    }
+    state.last_raw_response = Some(raw_body);
+
+    if let Some(usage) = usage {
+        state.usage += usage;
+        print_usage(&usage, &state.usage, config);
+    }
+
+    let mut raw_text = content;
+
+    if let Some(filter) = &config.response_filter {
+        raw_text = postprocess::apply_filter(config, &raw_text, filter);
+    }
+
+    let cleaned_text = raw_text.replace("`", "").trim().to_string();
+
+    state.history.push(Message {
+        role: "assistant".to_string(),
+        content: cleaned_text.clone(),
+    });
+
+    Ok(cleaned_text)
+}
+
+pub fn print_usage(turn: &Usage, session: &Usage, config: &Config) {
+    let mut line = format!(
+        "↑{} ↓{} tokens (session ↑{} ↓{})",
+        turn.prompt_tokens, turn.completion_tokens, session.prompt_tokens, session.completion_tokens
+    );
+    if let Some(price) = config.price_per_1k_tokens {
+        let cost = (session.prompt_tokens + session.completion_tokens) as f64 / 1000.0 * price;
+        line.push_str(&format!(", ~${:.4} this session", cost));
+    }
+    progress(config, line);
+}
+
+const PROGRESS_TAIL_LINES: usize = 40;
+
+pub struct ProgressWatch<'a> {
+    pub client: &'a Client,
+    pub api_key: &'a str,
+}
+
+impl ProgressWatch<'_> {
+    // Asking the model blocks the runtime thread the command runs on, which only a multi-threaded runtime allows;
+    // elsewhere (e.g. under `#[tokio::test]`) commands simply run to the end.
+    pub fn available() -> bool {
+        tokio::runtime::Handle::try_current().is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread)
+    }
+
+    pub fn should_abort(&self, command: &str, output: &exec::CapturedOutput, elapsed: Duration, config: &Config) -> bool {
+        let tail = config.redactor.redact(&output.tail(PROGRESS_TAIL_LINES));
+        if tail.trim().is_empty() {
+            return false;
+        }
+
+        let messages = vec![
+            Message {
+                role: "system".to_string(),
+                content: "You are watching a long-running command on behalf of Jade. \
+                    Reply with exactly ABORT if the output already shows the command will fail or is stuck \
+                    (e.g. tests are clearly broken), otherwise reply with exactly CONTINUE.".to_string(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: format!(
+                    "Command `{}` has been running for {}s. Last {} lines of output so far:\n{}",
+                    command, elapsed.as_secs(), PROGRESS_TAIL_LINES, tail
+                ),
+            },
+        ];
+
+        progress(config, format!("Sharing progress with the model ({}s elapsed)...", elapsed.as_secs()));
+
+        let reply = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(send_chat(self.client, self.api_key, messages, config))
+        });
+
+        match reply {
+            Ok((content, _)) => content.trim().to_uppercase().starts_with("ABORT"),
+            Err(e) => {
+                notice(config, style(format!("Progress check failed, letting the command run: {}", e)).yellow());
+                false
+            },
+        }
+    }
+}
+
+pub enum ExecutionOutcome {
+    // `command` is what actually ran, e.g. with a commit message edited during review. `notes` are Jade's own remarks
+    // on how it ended (killed, timed out, patch not applied), kept apart from stderr so a summary cannot drop them.
+    Ran { command: String, stdout: String, stderr: String, notes: Vec<String>, exit_code: Option<i32> },
+    DryRun,
+    Rejected { reason: String, violation: Violation },
+}
+
+pub fn handle_execution(
+    command: &str,
+    plan_approved: bool,
+    config: &Config,
+    work_dir: Option<&Path>,
+    watch: &ProgressWatch,
+    executor: &dyn CommandExecutor,
+) -> Result<ExecutionOutcome, Box<dyn std::error::Error>> {
+    if cancel::requested() {
+        return Err("Turn cancelled".into());
+    }
+
+    // The typed confirmation only stands in for the destructive check when the escalated git command is the whole
+    // line; anything chained to it (`git push -f; rm -rf build`) is checked like any other command.
+    let escalation = Escalation::detect(command).filter(|_| safety::segments(command).is_some_and(|s| s.len() == 1));
+
+    let destructive = safety::is_destructive(command).filter(|_| match &escalation {
+        Some(Escalation::HardReset) => !config.allow_hard_reset,
+        Some(Escalation::ForcePush) => config.block_force_push,
+        None => true,
+    });
+    if let Some(reason) = destructive {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: format!("Do NOT try to execute any destructive commands: {}.", reason),
+            violation: Violation::Destructive,
+        });
+    }
+
+    if safety::needs_privilege(command) && !config.allow_sudo {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: "Do NOT use sudo or other privilege escalation; Jade runs commands as the current user. \
+                Find an alternative that works without elevated privileges or explain via FINAL.".to_string(),
+            violation: Violation::Privileged,
+        });
+    }
+
+    if let Some(hint) = safety::needs_interactive_input(command) {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: format!("Commands run without a terminal, so they cannot open an editor or answer prompts: {}.", hint),
+            violation: Violation::Interactive,
+        });
+    }
+
+    if config.git_only && !safety::runs_only_git(command) {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: "Only git commands are allowed in git-only mode. Use one or more plain git commands \
+                without pipes into other programs or command substitution, or explain via FINAL.".to_string(),
+            violation: Violation::NotGit,
+        });
+    }
+
+    if let Err(reason) = config.policy.check(command) {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: format!("{} Propose a command the policy allows, or explain via FINAL that it blocks this step.", reason),
+            violation: Violation::Policy,
+        });
+    }
+
+    if command.contains("EXECUTE:") {
+        return Ok(ExecutionOutcome::Rejected {
+            reason: "Each EXECUTE command must be on its own line. Format:\n".to_string() +
+                "EXECUTE: <command>\n" +
+                "...\n" +
+                "EXECUTE: <command>",
+            violation: Violation::InlineExecute,
+        });
+    }
+
+    if config.dry_run {
+        notice(config, format!("{} {}", style("[dry-run] Would run:").yellow().bold(), command));
+        return Ok(ExecutionOutcome::DryRun);
+    }
+
+    let mut confirmed = plan_approved || config.auto_confirm;
+
+    if let Some(escalation) = &escalation
+        && !plan_approved
+        && !safety::confirm_escalated(config, command, escalation, work_dir)? {
+        notice(config, style("Confirmation phrase did not match, command skipped.").yellow());
+        return Ok(ExecutionOutcome::Rejected {
+            reason: format!("The user did not confirm this command because {}. Do not retry it; propose a safer alternative or explain via FINAL.", escalation.description()),
+            violation: Violation::UserRejected,
+        });
+    }
+    confirmed |= escalation.is_some();
+
+    let mut command = command.to_string();
+
+    if !cfg!(target_os = "windows")
+        && console::user_attended()
+        && !config.auto_confirm
+        && let Some(commit) = CommitCommand::parse(&command) {
+        confirmed = true;
+        match commit.review(config, &git::hooks_for(&command, work_dir))? {
+            CommitReview::Unchanged => {},
+            CommitReview::Edited(message) => command = commit.with_message(&message),
+            CommitReview::Cancelled => {
+                return Ok(ExecutionOutcome::Rejected {
+                    reason: "The user cancelled this commit. Ask via FINAL how they would like to proceed.".to_string(),
+                    violation: Violation::UserRejected,
+                });
+            },
+        }
+    }
+
+    if let Some(check) = patch::check_command(&command) {
+        progress(config, format!("Dry run: {}", check));
+
+        let check_output = executor.run(&check, work_dir, None)?;
+
+        if !check_output.success() {
+            let problems = String::from_utf8_lossy(&check_output.stderr).to_string();
+            notice(config, style("✖ Patch does not apply cleanly, skipping").red());
+            if config.quiet && !problems.is_empty() { notice(config, style(&problems).red()); }
+            return Ok(ExecutionOutcome::Ran {
+                command: check.clone(),
+                stdout: String::new(),
+                stderr: problems,
+                notes: vec![format!("Dry run `{}` failed, so the patch was NOT applied.", check)],
+                exit_code: check_output.exit_code,
+            });
+        }
+
+        notice(config, style("✔ Patch applies cleanly").green());
+
+        if console::user_attended()
+            && !config.auto_confirm
+            && !Confirm::new().with_prompt("Apply it for real?").default(true).interact()? {
+            return Ok(ExecutionOutcome::Rejected {
+                reason: "The user chose not to apply this patch. Ask via FINAL how they would like to proceed.".to_string(),
+                violation: Violation::UserRejected,
+            });
+        }
+        confirmed |= console::user_attended();
+    }
+
+    if !confirmed {
+        if !console::user_attended() {
+            notice(config, style("Cannot ask for confirmation without a terminal, skipping. Set JADE_AUTO_CONFIRM=1 to run commands unattended.").yellow());
+            return Ok(ExecutionOutcome::Rejected {
+                reason: "Jade could not ask the user to confirm this command, so it was not run. Explain via FINAL what you wanted to run.".to_string(),
+                violation: Violation::UserRejected,
+            });
+        }
+
+        notice(config, format!("{} {}", style("Proposed command:").bold(), style(&command).cyan()));
+        safety::print_hooks(config, &git::hooks_for(&command, work_dir));
+        if !Confirm::new().with_prompt("Run this?").default(false).interact()? {
+            return Ok(ExecutionOutcome::Rejected {
+                reason: "The user rejected this command. Propose a different approach or ask via FINAL how they would like to proceed.".to_string(),
+                violation: Violation::UserRejected,
+            });
+        }
+    }
+
+    emit(config, Event::CommandExecuted { command: &command });
+
+    let mut check_progress = |so_far: &exec::CapturedOutput, elapsed: Duration| watch.should_abort(&command, so_far, elapsed, config);
+    let progress = config.stream_progress().filter(|_| ProgressWatch::available()).map(|interval| exec::Progress {
+        interval,
+        should_abort: &mut check_progress,
+    });
+
+    let output = executor.run(&command, work_dir, progress)?;
+    if let Some(path) = &config.audit_log
+        && let Err(e) = audit::record(path, &command, output.exit_code) {
+        notice(config, style(format!("Could not write to the audit log {}: {}", path.display(), e)).yellow());
+    }
+    if output.interrupted {
+        return Err("Turn cancelled while the command was running".into());
+    }
+
+    let stdout = exec::decode_output(&output.stdout);
+    let mut stderr = exec::decode_output(&output.stderr);
+    if config.login_shell {
+        stderr = shell::strip_job_control_warnings(&stderr);
+    }
+
+    let mut notes = Vec::new();
+    if output.runaway {
+        notes.push(format!(
+            "Jade terminated this command because it produced more than {} bytes or {} lines of output. \
+            Use a more targeted command (e.g. limit with -n, --stat or a path filter).",
+            config.max_output_bytes, config.max_output_lines
+        ));
+    }
+
+    if output.timed_out {
+        notes.push(format!(
+            "Jade killed this command because it did not finish within {} seconds. It may have been waiting for input \
+            (an editor or a prompt); use non-interactive flags, or split the work into smaller steps.",
+            config.command_timeout_secs
+        ));
+    }
+
+    if output.aborted_early {
+        notes.push("Jade stopped this command early because the partial output showed it was failing. \
+            Fix the problem shown above before running it again.".to_string());
+    }
+
+    emit(config, Event::CommandOutput {
+        command: &command,
+        stdout: &stdout,
+        stderr: &notes.iter().fold(stderr.clone(), |all, note| format!("{}\n{}", all, note)),
+        exit_code: output.exit_code,
+        success: output.success(),
+    });
+
+    Ok(ExecutionOutcome::Ran { command, stdout, stderr, notes, exit_code: output.exit_code })
+}
+
+// What the model is told about a command that ran: a structured summary of a failure when there is one, otherwise the
+// (truncated) output, followed by Jade's notes and the exit code.
+fn command_feedback(config: &Config, step: &str, output: &str, error: &str, notes: &[String], exit_code: Option<i32>) -> String {
+    let mut feedback = String::new();
+    if let Some(summary) = diagnostics::summarize_failure(step, output, error, exit_code) {
+        feedback.push_str(&summary);
+    } else {
+        let output = truncate_output(output, config.feedback_max_chars, config.feedback_max_lines);
+        feedback.push_str(&format!("Output of `{}`:\n{}\n", step, output));
+        if !error.is_empty() {
+            let error = truncate_output(error, config.feedback_max_chars, config.feedback_max_lines);
+            feedback.push_str(&format!("ERROR: {}\n", error));
+        }
+    }
+    for note in notes {
+        feedback.push_str(&format!("{}\n", note));
+    }
+    match exit_code {
+        Some(code) => feedback.push_str(&format!("Exit code: {}\n", code)),
+        None => feedback.push_str("Exit code: none (the command was killed by a signal)\n"),
+    }
+    feedback
+}
+
+pub async fn run_turn(
+    client: &Client,
+    api_key: &str,
+    state: &mut ReplState,
+    config: &Config,
+    executor: &dyn CommandExecutor,
+    sessions: Option<&SessionStore>,
+    mut current_input: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    state.turn = Some(TurnStatus::default());
+
+    let mut git_context = format!(
+        "GIT STATUS:\n{}\n\nGIT DIFF:\n{}",
+        get_git_status(false, state.work_dir.as_deref()),
+        get_git_diff(&config.diff_exclude, config.diff_context_lines, state.work_dir.as_deref())
+    );
+    let remotes = git::remotes(state.work_dir.as_deref());
+    let remotes = if remotes.is_empty() { "none (nothing can be pushed or pulled)".to_string() } else { remotes.join(", ") };
+    git_context = format!("REMOTES: {}\n\n{}", remotes, git_context);
+    if let Some(branch) = git::get_default_branch(state.work_dir.as_deref(), config.default_branch.as_deref()) {
+        git_context = format!("DEFAULT BRANCH: {}\n\n{}", branch, git_context);
+    }
+    if let Some(dir) = &state.work_dir {
+        git_context = format!("ACTIVE WORKTREE: {}\n\n{}", dir.display(), git_context);
+    }
+    let git_context = config.redactor.redact(&git_context);
+    state.last_context = Some(git_context.clone());
+    let mut attempts: u32 = 0;
+
+    emit(config, Event::Prompt { text: &current_input });
+    progress(config, "Understanding user input...");
+
+    let watch = ProgressWatch { client, api_key };
+    let mut corrections = Corrections::default();
+
+    loop {
+        if let Some(sessions) = sessions {
+            sessions.checkpoint(&state.history);
+        }
+
+        let abort_reason = if attempts > config.max_attempts {
+            Some(format!("Too many attempts (limit {})", config.max_attempts))
+        } else {
+            corrections.exhausted(&config.retry_limits).map(|violation| format!("The model {}", violation.description()))
+        };
+        if let Some(reason) = abort_reason {
+            emit(config, Event::Error { message: &format!("ABORTING: {}", reason) });
+            state.history.push(Message {
+                role: "user".to_string(),
+                content: format!(
+                    "Jade aborted the previous request: {}. Commands before that may have run. \
+                    If the user asks to continue, check the repository state before retrying.",
+                    reason
+                ),
+            });
+            state.turn().outcome = TurnOutcome::Aborted;
+            break;
+        }
+
+        let mut response = get_llm_response(client, api_key, &current_input, &git_context, state, config).await?;
+
+        current_input = String::new();
+        let mut user_edited_plan = false;
+
+        let mut parsed = protocol::parse_response(&response);
+
+        if let ParsedResponse::Commands(lines) = &parsed
+            && state.review_plans
+            && !protocol::planned_commands(lines).is_empty()
+            && console::user_attended() {
+            match Plan::from_response(&response).edit()? {
+                Some(plan) => {
+                    response = plan.to_response();
+                    parsed = protocol::parse_response(&response);
+                    user_edited_plan = true;
+                    if let Some(last) = state.history.last_mut() {
+                        last.content = response.clone();
+                    }
+                },
+                None => {
+                    notice(config, style("Plan discarded, nothing was executed.").yellow());
+                    add_llm_correction(config, &response, "The user discarded this plan. Ask via FINAL how they would like to proceed.", &mut state.history);
+                    state.turn().record_rejection(protocol::planned_commands(lines).len());
+                    corrections.record(Violation::UserRejected);
+                    attempts += 1;
+                    continue;
+                },
+            }
+        }
+
+        let lines = match parsed {
+            ParsedResponse::Final(message) => {
+                emit(config, Event::FinalAnswer { text: &message });
+                break;
+            },
+            ParsedResponse::Malformed { reason, violation } => {
+                add_llm_correction(config, &response, &reason, &mut state.history);
+                corrections.record(violation);
+                attempts += 1;
+                continue;
+            },
+            ParsedResponse::Commands(lines) => lines,
+        };
+        let planned_commands = protocol::planned_commands(&lines);
+
+        let risks = safety::plan_risks(&planned_commands);
+        let plan_approved = !risks.is_empty();
+        if plan_approved && !config.dry_run {
+            let mut plan_hooks: Vec<&str> = Vec::new();
+            for hook in planned_commands.iter().flat_map(|c| git::hooks_for(c, state.work_dir.as_deref())) {
+                if !plan_hooks.contains(&hook) {
+                    plan_hooks.push(hook);
+                }
+            }
+
+            if !safety::confirm_plan(config, &risks, &plan_hooks)? {
+                notice(config, style("Plan rejected, nothing was executed.").yellow());
+                add_llm_correction(config, &response, &format!(
+                    "The user rejected this plan because it would {}. Propose a less destructive approach or explain via FINAL.",
+                    risks.join(", ")), &mut state.history);
+                corrections.record(Violation::UserRejected);
+                state.turn().record_rejection(planned_commands.len());
+                attempts += 1;
+                continue;
+            }
+        }
+
+        let mut executed_something = false;
+        let mut feedback_buffer = String::new();
+        let mut rationale: Option<String> = None;
+
+        for line in lines {
+            let command_cleaned = match line {
+                Line::Why(reason) => {
+                    rationale = Some(reason).filter(|r| !r.is_empty());
+                    continue;
+                },
+                Line::Set(assignment) => {
+                    match state.variables.assign(&assignment) {
+                        Ok(name) => {
+                            executed_something = true;
+                            feedback_buffer.push_str(&format!("Variable {} set.\n", name));
+                        },
+                        Err(reason) => {
+                            add_llm_correction(config, &format!("SET: {}", assignment), &reason, &mut state.history);
+                            corrections.record(Violation::BadVariable);
+                        },
+                    }
+                    continue;
+                },
+                Line::Unprefixed(text) => {
+                    add_llm_correction(config, &text, "Command should start with `EXECUTE`.", &mut state.history);
+                    corrections.record(Violation::MissingPrefix);
+                    continue;
+                },
+                Line::Execute(command) if command.is_empty() => {
+                    add_llm_correction(config, "EXECUTE:", "EXECUTE lines must contain a command. Leave out lines with nothing to run.", &mut state.history);
+                    corrections.record(Violation::EmptyExecute);
+                    continue;
+                },
+                Line::Execute(command) => command,
+            };
+            let command_cleaned = command_cleaned.as_str();
+
+            match rationale.take() {
+                Some(reason) => notice(config, format!("{} {}", style("Why:").cyan().bold(), style(reason).cyan())),
+                None if config.require_rationale && !user_edited_plan => {
+                    add_llm_correction(config, command_cleaned, "Every EXECUTE line must be directly preceded by a `WHY: <reason>` line. The command was not run.", &mut state.history);
+                    corrections.record(Violation::MissingRationale);
+                    state.turn().record_rejection(1);
+                    continue;
+                },
+                None => {},
+            }
+
+            let command_cleaned = match state.variables.substitute(command_cleaned) {
+                Ok(substituted) => substituted,
+                Err(reason) => {
+                    add_llm_correction(config, command_cleaned, &reason, &mut state.history);
+                    corrections.record(Violation::BadVariable);
+                    continue;
+                },
+            };
+            let command_cleaned = command_cleaned.trim();
+
+            if command_cleaned.is_empty() {
+                continue;
+            }
+
+            // `a && b` runs step by step so the feedback can say exactly which step failed.
+            let steps = safety::and_chain(command_cleaned).unwrap_or_else(|| vec![command_cleaned.to_string()]);
+            for (index, step) in steps.iter().enumerate() {
+                let step = step.as_str();
+                if config.explain {
+                    match explain::command(client, api_key, config, step).await {
+                        Ok(explanation) => notice(config, format!("{} {}", style("Explain:").magenta().bold(), explanation)),
+                        Err(e) => notice(config, style(format!("Could not explain this command: {}", e)).yellow()),
+                    }
+                }
+
+                let head_before = git::head(state.work_dir.as_deref());
+                let succeeded = match handle_execution(step, plan_approved, config, state.work_dir.as_deref(), &watch, executor)? {
+                    ExecutionOutcome::Rejected { reason, violation } => {
+                        add_llm_correction(config, step, &reason, &mut state.history);
+                        corrections.record(violation);
+                        state.turn().record_rejection(1);
+                        false
+                    },
+                    ExecutionOutcome::DryRun => {
+                        executed_something = true;
+                        feedback_buffer.push_str(&format!(
+                            "[dry-run] `{}` was not executed. Assume it succeeded with no output.\n", step
+                        ));
+                        true
+                    },
+                    ExecutionOutcome::Ran { command, stdout: output, stderr: error, notes, exit_code } => {
+                        executed_something = true;
+                        state.turn().commands_run += 1;
+                        state.executed.push(command);
+                        if exit_code == Some(0) && undo::changes_something(step) {
+                            state.last_executed = Some(ExecutedCommand { command: step.to_string(), head_before });
+                        }
+                        feedback_buffer.push_str(&command_feedback(config, step, &output, &error, &notes, exit_code));
+                        if diagnostics::is_ref_error(&error) {
+                            feedback_buffer.push_str(&diagnostics::describe_refs(&git::list_refs(state.work_dir.as_deref())));
+                        }
+                        exit_code == Some(0)
+                    },
+                };
+
+                let skipped = &steps[index + 1..];
+                if !succeeded && !skipped.is_empty() {
+                    executed_something = true;
+                    feedback_buffer.push_str(&format!(
+                        "Step {} of {} in `{}` failed, so these steps were NOT run: {}\n",
+                        index + 1,
+                        steps.len(),
+                        command_cleaned,
+                        skipped.iter().map(|s| format!("`{}`", s)).collect::<Vec<_>>().join(", ")
+                    ));
+                    break;
+                }
+            }
+        }
+
+        if executed_something {
+            state.history.push(Message {
+                role: "user".to_string(),
+                content: config.redactor.redact(&feedback_buffer),
+            });
+        }
+
+        attempts += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockExecutor;
+
+    fn test_config() -> Config {
+        let mut config = Config::load(&std::env::temp_dir().join("jade-no-config")).unwrap();
+        config.auto_confirm = true;
+        config.audit_log = None;
+        config
+    }
+
+    #[test]
+    fn sudo_commands_are_rejected() {
+        let config = test_config();
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default();
+
+        let outcome = handle_execution("sudo git status", false, &config, None, &watch, &executor).unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Rejected { violation: Violation::Privileged, .. }));
+        assert!(executor.ran().is_empty());
+    }
+
+    #[test]
+    fn sudo_commands_run_when_allowed() {
+        let mut config = test_config();
+        config.allow_sudo = true;
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default();
+
+        let outcome = handle_execution("sudo git status", false, &config, None, &watch, &executor).unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Ran { .. }));
+        assert_eq!(executor.ran(), vec!["sudo git status"]);
+    }
+
+    #[test]
+    fn destructive_commands_never_reach_the_executor() {
+        let config = test_config();
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default();
+
+        let outcome = handle_execution("rm -rf src", false, &config, None, &watch, &executor).unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Rejected { violation: Violation::Destructive, .. }));
+        assert!(executor.ran().is_empty());
+    }
+
+    #[test]
+    fn commands_chained_to_a_force_push_are_still_checked() {
+        let config = test_config();
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default();
+
+        for command in ["git push -f origin main; rm -rf build", "git push --force origin main | rm -rf ~"] {
+            let outcome = handle_execution(command, true, &config, None, &watch, &executor).unwrap();
+            assert!(matches!(outcome, ExecutionOutcome::Rejected { violation: Violation::Destructive, .. }), "{}", command);
+        }
+        assert!(executor.ran().is_empty());
+    }
+
+    #[test]
+    fn blocked_force_pushes_are_refused() {
+        let mut config = test_config();
+        config.block_force_push = true;
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default();
+
+        let outcome = handle_execution("git push --force origin main", true, &config, None, &watch, &executor).unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Rejected { violation: Violation::Destructive, .. }));
+        assert!(executor.ran().is_empty());
+    }
+
+    #[test]
+    fn canned_output_is_passed_through() {
+        let config = test_config();
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+        let executor = MockExecutor::default().reply("git push", "", "rejected: non-fast-forward", 1);
+
+        match handle_execution("git push", false, &config, None, &watch, &executor).unwrap() {
+            ExecutionOutcome::Ran { stderr, exit_code, .. } => {
+                assert!(stderr.contains("non-fast-forward"));
+                assert_eq!(exit_code, Some(1));
+            },
+            _ => panic!("git push should have run"),
+        }
+        assert_eq!(executor.ran(), vec!["git push"]);
+    }
+
+    #[test]
+    fn notes_are_kept_next_to_a_failure_summary() {
+        let config = test_config();
+        let output = include_str!("../tests/fixtures/cargo_test_failures.txt");
+        let notes = vec!["Jade killed this command because it did not finish within 30 seconds.".to_string()];
+
+        let feedback = command_feedback(&config, "cargo test", output, "", &notes, None);
+        assert!(feedback.starts_with("Structured summary of `cargo test` output:"));
+        assert!(feedback.contains("Jade killed this command"));
+        assert!(feedback.ends_with("Exit code: none (the command was killed by a signal)\n"));
+    }
+
+    #[tokio::test]
+    async fn progress_checks_are_skipped_on_a_current_thread_runtime() {
+        assert!(!ProgressWatch::available());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn progress_checks_run_on_a_multi_threaded_runtime() {
+        assert!(ProgressWatch::available());
+    }
+
+    #[test]
+    fn exit_codes_are_reported() {
+        let config = test_config();
+        let client = Client::new();
+        let watch = ProgressWatch { client: &client, api_key: "" };
+
+        let outcome = handle_execution("exit 3", false, &config, None, &watch, &config.executor()).unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Ran { exit_code: Some(3), .. }));
+    }
+}
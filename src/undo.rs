@@ -0,0 +1,115 @@
+use reqwest::Client;
+
+use crate::api::{send_chat, Message};
+use crate::config::Config;
+use crate::shell;
+
+const UNDO_PROMPT: &str = "You reverse shell commands run in a git repository. \
+    Given a command that ran successfully and the commit HEAD pointed to before it, reply with one shell command \
+    that undoes its effect while keeping the user's work (prefer soft resets, restores and reverts over anything that discards changes). \
+    Reply with the command only, or NONE if it cannot be undone safely. Do not use the EXECUTE or FINAL protocol.";
+
+pub struct ExecutedCommand {
+    pub command: String,
+    pub head_before: Option<String>,
+}
+
+const READ_ONLY_SUBCOMMANDS: &[&str] = &[
+    "status", "log", "diff", "show", "rev-parse", "ls-files", "blame", "describe", "shortlog", "grep", "cat-file", "rev-list",
+];
+
+// Commands that only inspect the repository are not worth undoing, so /undo skips past them.
+pub fn changes_something(command: &str) -> bool {
+    let Some(args) = shell::split(command) else { return true };
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["git", subcommand, ..] if READ_ONLY_SUBCOMMANDS.contains(subcommand) => false,
+        ["git", "branch" | "tag" | "remote", rest @ ..] => {
+            !rest.iter().all(|a| matches!(*a, "-a" | "--all" | "-r" | "--remotes" | "-v" | "-vv" | "--verbose" | "-l" | "--list"))
+        },
+        ["git", "stash", "list" | "show", ..] => false,
+        _ => true,
+    }
+}
+
+// Inverses for common commands; anything else is left to the model.
+pub fn known_inverse(executed: &ExecutedCommand) -> Option<String> {
+    let args = shell::split(&executed.command)?;
+    let (program, subcommand, rest) = match args.as_slice() {
+        [program, subcommand, rest @ ..] => (program.as_str(), subcommand.as_str(), rest),
+        _ => return None,
+    };
+    if program != "git" {
+        return None;
+    }
+
+    let names: Vec<&String> = rest.iter().filter(|a| !a.starts_with('-')).collect();
+    match subcommand {
+        "commit" => executed.head_before.as_ref().map(|head| format!("git reset --soft {}", head)),
+        "add" => {
+            let paths: Vec<&String> = names.into_iter().filter(|p| p.as_str() != ".").collect();
+            if paths.is_empty() || rest.iter().any(|a| a == "-A" || a == "--all") {
+                Some("git reset -q".to_string())
+            } else {
+                Some(format!("git reset -q -- {}", shell::join(&paths)))
+            }
+        },
+        "branch" if rest.len() == 1 && names.len() == 1 => Some(format!("git branch -d {}", shell::quote(names[0]))),
+        "tag" if rest.len() == 1 && names.len() == 1 => Some(format!("git tag -d {}", shell::quote(names[0]))),
+        "stash" if rest.is_empty() || rest[0] == "push" => Some("git stash pop".to_string()),
+        _ => None,
+    }
+}
+
+pub async fn ask_inverse(client: &Client, api_key: &str, config: &Config, executed: &ExecutedCommand) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let messages = vec![
+        Message { role: "system".to_string(), content: UNDO_PROMPT.to_string() },
+        Message {
+            role: "user".to_string(),
+            content: format!(
+                "COMMAND: {}\nHEAD BEFORE: {}",
+                executed.command,
+                executed.head_before.as_deref().unwrap_or("(no commits yet)")
+            ),
+        },
+    ];
+
+    let (content, _) = send_chat(client, api_key, messages, config).await?;
+    let inverse = content.trim().trim_matches('`').trim().to_string();
+    Ok((!inverse.is_empty() && inverse != "NONE" && !inverse.contains('\n')).then_some(inverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn executed(command: &str) -> ExecutedCommand {
+        ExecutedCommand { command: command.to_string(), head_before: Some("abc123".to_string()) }
+    }
+
+    #[test]
+    fn common_commands_have_known_inverses() {
+        assert_eq!(known_inverse(&executed("git commit -m 'Fix it'")).as_deref(), Some("git reset --soft abc123"));
+        assert_eq!(known_inverse(&executed("git add -A")).as_deref(), Some("git reset -q"));
+        assert_eq!(known_inverse(&executed("git add src/main.rs 'a b.txt'")).as_deref(), Some("git reset -q -- src/main.rs 'a b.txt'"));
+        assert_eq!(known_inverse(&executed("git branch feature")).as_deref(), Some("git branch -d feature"));
+        assert_eq!(known_inverse(&executed("git stash")).as_deref(), Some("git stash pop"));
+    }
+
+    #[test]
+    fn inspection_commands_change_nothing() {
+        for command in ["git status", "git log --oneline -n 5", "git branch -a", "git remote -v", "git stash list"] {
+            assert!(!changes_something(command), "{}", command);
+        }
+        for command in ["git commit -m x", "git branch feature", "git branch -D feature", "git stash", "rm file", "git add . && git commit"] {
+            assert!(changes_something(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn other_commands_are_left_to_the_model() {
+        assert!(known_inverse(&executed("git rebase main")).is_none());
+        assert!(known_inverse(&executed("git branch -D feature")).is_none());
+        assert!(known_inverse(&ExecutedCommand { command: "git commit -m x".to_string(), head_before: None }).is_none());
+        assert!(known_inverse(&executed("ls")).is_none());
+    }
+}
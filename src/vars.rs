@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use crate::shell;
+
+#[derive(Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    pub fn assign(&mut self, assignment: &str) -> Result<String, String> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| "SET lines must look like `SET: NAME=value`.".to_string())?;
+
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err(format!("`{}` is not a valid variable name. Use letters, digits and underscores.", name));
+        }
+
+        self.values.insert(name.to_string(), value.trim().to_string());
+        Ok(name.to_string())
+    }
+
+    pub fn substitute(&self, command: &str) -> Result<String, String> {
+        let mut result = String::new();
+        let mut rest = command;
+        let mut quote = None;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else { break };
+
+            let name = rest[start + 2..start + 2 + len].trim();
+            let value = self
+                .values
+                .get(name)
+                .ok_or_else(|| format!("Variable `{}` is not set. Set it first with `SET: {}=<value>`.", name, name))?;
+
+            quote = quote_after(&rest[..start], quote);
+            result.push_str(&rest[..start]);
+            result.push_str(&quote_for(value, quote));
+            rest = &rest[start + 2 + len + 2..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+// The quote a shell would be inside after reading `text`, starting inside `quote`.
+fn quote_after(text: &str, mut quote: Option<char>) -> Option<char> {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {},
+            (_, '\\') => {
+                chars.next();
+            },
+            (Some('"'), '"') => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            _ => {},
+        }
+    }
+    quote
+}
+
+// Quotes a value so the shell reads it back literally wherever it is substituted.
+fn quote_for(value: &str, quote: Option<char>) -> String {
+    match quote {
+        Some('\'') => value.replace('\'', r"'\''"),
+        Some('"') => value.chars().fold(String::new(), |mut escaped, c| {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        }),
+        _ => shell::quote(value),
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut vars = Variables::default();
+        vars.assign("BRANCH=feature/login").unwrap();
+        vars.assign("MESSAGE = Fix the \"login\" page's $HOME check").unwrap();
+        vars
+    }
+
+    #[test]
+    fn substitutes_quoted_values() {
+        let vars = variables();
+        assert_eq!(vars.substitute("git switch {{BRANCH}}").unwrap(), "git switch feature/login");
+        assert_eq!(vars.substitute("git commit -m {{ MESSAGE }}").unwrap(), r#"git commit -m 'Fix the "login" page'\''s $HOME check'"#);
+        assert_eq!(vars.substitute("git log {{BRANCH}} -- {{").unwrap(), "git log feature/login -- {{");
+    }
+
+    #[test]
+    fn values_inside_quotes_are_escaped_for_that_quote() {
+        let vars = variables();
+        assert_eq!(
+            vars.substitute(r#"git commit -m "WIP: {{MESSAGE}}""#).unwrap(),
+            r#"git commit -m "WIP: Fix the \"login\" page's \$HOME check""#
+        );
+        assert_eq!(vars.substitute("git commit -m 'WIP: {{MESSAGE}}'").unwrap(), r#"git commit -m 'WIP: Fix the "login" page'\''s $HOME check'"#);
+        assert_eq!(shell::split(&vars.substitute(r#"echo "on {{BRANCH}}: {{MESSAGE}}""#).unwrap()).unwrap(), vec![
+            "echo".to_string(),
+            r#"on feature/login: Fix the "login" page's $HOME check"#.to_string(),
+        ]);
+    }
+
+    #[test]
+    fn unknown_names_are_an_error() {
+        let error = variables().substitute("git switch {{TARGET}}").unwrap_err();
+        assert!(error.contains("`TARGET` is not set"));
+        assert!(Variables::default().assign("1ST=x").is_err());
+    }
+}
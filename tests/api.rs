@@ -0,0 +1,105 @@
+use jade::api::{send_chat, Message};
+use jade::config::Config;
+use jade::exec::MockExecutor;
+use jade::repl::{ReplState, TurnOutcome};
+use jade::turn::run_turn;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn config_for(server: &MockServer) -> Config {
+    let mut config = Config::load(&std::env::temp_dir().join("jade-no-config")).unwrap();
+    config.base_url = server.uri();
+    config.auto_confirm = true;
+    config.api_retries = 0;
+    config.audit_log = None;
+    config
+}
+
+fn streamed(content: &str) -> ResponseTemplate {
+    let chunk = serde_json::json!({ "choices": [{ "delta": { "content": content } }] });
+    ResponseTemplate::new(200).set_body_string(format!("data: {}\n\ndata: [DONE]\n\n", chunk))
+}
+
+#[tokio::test]
+async fn final_response_ends_the_turn_without_running_anything() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(streamed("FINAL: Nothing to do."))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let executor = MockExecutor::default();
+    let mut state = ReplState::default();
+    run_turn(&Client::new(), "key", &mut state, &config_for(&server), &executor, None, "anything to commit?".to_string())
+        .await
+        .unwrap();
+
+    assert!(executor.ran().is_empty());
+    assert!(matches!(state.turn().outcome, TurnOutcome::Success));
+}
+
+#[tokio::test]
+async fn execute_lines_go_through_the_executor() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(streamed("EXECUTE: git log --oneline -n 1"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(streamed("FINAL: The last commit is abc123."))
+        .mount(&server)
+        .await;
+
+    let executor = MockExecutor::default().reply("git log --oneline -n 1", "abc123 Fix typo\n", "", 0);
+    let mut state = ReplState::default();
+    run_turn(&Client::new(), "key", &mut state, &config_for(&server), &executor, None, "what was my last commit?".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(executor.ran(), vec!["git log --oneline -n 1"]);
+    assert_eq!(state.turn().commands_run, 1);
+    assert!(state.history.iter().any(|m| m.role == "user" && m.content.contains("abc123 Fix typo")));
+}
+
+#[tokio::test]
+async fn token_usage_adds_up_per_conversation() {
+    let server = MockServer::start().await;
+    let chunk = serde_json::json!({
+        "choices": [{ "delta": { "content": "FINAL: Done." } }],
+        "usage": { "prompt_tokens": 100, "completion_tokens": 7 },
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("data: {}\n\ndata: [DONE]\n\n", chunk)))
+        .mount(&server)
+        .await;
+
+    let config = config_for(&server);
+    let executor = MockExecutor::default();
+    let mut state = ReplState::default();
+    for prompt in ["first", "second"] {
+        run_turn(&Client::new(), "key", &mut state, &config, &executor, None, prompt.to_string()).await.unwrap();
+    }
+    assert_eq!((state.usage.prompt_tokens, state.usage.completion_tokens), (200, 14));
+}
+
+#[tokio::test]
+async fn empty_choices_are_an_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"choices":[]}"#))
+        .mount(&server)
+        .await;
+
+    let messages = vec![Message { role: "user".to_string(), content: "hi".to_string() }];
+    let error = send_chat(&Client::new(), "key", messages, &config_for(&server)).await.unwrap_err();
+    assert!(error.to_string().contains("no choices"));
+}